@@ -0,0 +1,578 @@
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{in_use, CleanOptions, InUse, Processes, Project};
+
+/// A Cargo target directory configured to live outside of the project, through
+/// `CARGO_TARGET_DIR` or `build.target-dir` in a `.cargo/config.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetDir {
+    pub path: PathBuf,
+    /// More than one of the scanned projects builds into the directory, set by
+    /// [`group_target_dirs`]
+    pub shared: bool,
+}
+
+impl TargetDir {
+    /// Whether a build holds the directory's lock, or a process has its working directory or an
+    /// open file in it, see [`Project::in_use`]
+    pub fn in_use(&self) -> Option<InUse> {
+        self.in_use_with(&Processes::snapshot())
+    }
+
+    /// Like [`TargetDir::in_use`], looking for processes in a snapshot taken beforehand
+    pub fn in_use_with(&self, processes: &Processes) -> Option<InUse> {
+        target_dir_in_use(&self.path, processes)
+    }
+
+    /// Deletes the target directory and its contents, unless it's [in use](TargetDir::in_use)
+    pub fn clean(&self) {
+        if let Err(in_use) = self.clean_with(&CleanOptions::default(), &Processes::snapshot()) {
+            eprintln!("not cleaning {:?}, it {in_use}", self.path);
+        }
+    }
+
+    /// Like [`TargetDir::clean`], returning why nothing was removed when the directory is in use.
+    /// Only [`CleanOptions::allow_in_use`] applies.
+    pub fn clean_with(&self, options: &CleanOptions, processes: &Processes) -> Result<(), InUse> {
+        if !options.allow_in_use {
+            if let Some(in_use) = self.in_use_with(processes) {
+                return Err(in_use);
+            }
+        }
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            eprintln!("error removing directory {:?}: {:?}", self.path, e);
+        }
+        Ok(())
+    }
+}
+
+fn target_dir_in_use(target_dir: &Path, processes: &Processes) -> Option<InUse> {
+    in_use::held_cargo_lock(target_dir)
+        .map(InUse::BuildLock)
+        .or_else(|| processes.using(target_dir))
+}
+
+const CARGO_ENV_VARS: [&str; 6] = [
+    "CARGO_TARGET_DIR",
+    "CARGO_BUILD_TARGET_DIR",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "HOME",
+    "USERPROFILE",
+];
+
+/// The environment variables Cargo and rustup read their settings from. [`Default`] is an empty
+/// environment, where only the config files of a project and its parents apply.
+#[derive(Debug, Clone, Default)]
+pub struct CargoEnv(pub HashMap<String, OsString>);
+
+/// [`CargoEnv::from_process`], read once for every project
+static PROCESS_ENV: OnceLock<CargoEnv> = OnceLock::new();
+
+impl CargoEnv {
+    /// The variables of this process
+    pub fn from_process() -> Self {
+        Self(
+            CARGO_ENV_VARS
+                .iter()
+                .filter_map(|var| Some((var.to_string(), env::var_os(var)?)))
+                .collect(),
+        )
+    }
+
+    /// The variables of this process as they were when first asked for
+    pub(crate) fn process() -> &'static Self {
+        PROCESS_ENV.get_or_init(Self::from_process)
+    }
+
+    fn var(&self, var: &str) -> Option<&OsStr> {
+        self.0
+            .get(var)
+            .map(OsString::as_os_str)
+            .filter(|v| !v.is_empty())
+    }
+
+    fn home_dir(&self, var: &str, dir_name: &str) -> Option<PathBuf> {
+        self.var(var).map(PathBuf::from).or_else(|| {
+            self.var("HOME")
+                .or_else(|| self.var("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(dir_name))
+        })
+    }
+}
+
+/// Resolves the effective target directory for the Cargo project in `project_dir`, the same way
+/// Cargo does when invoked from there. `None` means the default `target` is used.
+pub(crate) fn configured_target_dir(project_dir: &Path, env: &CargoEnv) -> Option<TargetDir> {
+    let env_target = ["CARGO_TARGET_DIR", "CARGO_BUILD_TARGET_DIR"]
+        .iter()
+        .find_map(|var| env.var(var))
+        .map(PathBuf::from);
+
+    resolve_target_dir(
+        project_dir,
+        env_target,
+        env.home_dir("CARGO_HOME", ".cargo"),
+    )
+}
+
+pub(crate) fn resolve_target_dir(
+    project_dir: &Path,
+    env_target: Option<PathBuf>,
+    cargo_home: Option<PathBuf>,
+) -> Option<TargetDir> {
+    // Relative paths in the environment are relative to where cargo is run
+    if let Some(path) = env_target {
+        return Some(TargetDir {
+            path: normalise(&project_dir.join(path)),
+            shared: false,
+        });
+    }
+
+    // Config files closer to the project take precedence, $CARGO_HOME comes last
+    let config_dirs = project_dir
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .chain(cargo_home);
+
+    for config_dir in config_dirs {
+        let Some(target_dir) = ["config.toml", "config"]
+            .iter()
+            .find_map(|f| read_config_target_dir(&config_dir.join(f)))
+        else {
+            continue;
+        };
+
+        // Relative to the directory containing `.cargo`
+        let base = config_dir.parent().unwrap_or(&config_dir);
+        return Some(TargetDir {
+            path: normalise(&base.join(target_dir)),
+            shared: false,
+        });
+    }
+
+    None
+}
+
+/// Resolves `.` and `..` without touching the filesystem, so a target directory such as
+/// `../target` isn't taken to be inside the project
+fn normalise(path: &Path) -> PathBuf {
+    let mut normalised = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalised.pop() => {}
+            component => normalised.push(component),
+        }
+    }
+    normalised
+}
+
+/// Merges the target directories of the scanned projects, each directory is returned once along
+/// with the projects building into it, in the order they were first seen. Directories used by
+/// more than one project are marked as [shared](TargetDir::shared).
+pub fn group_target_dirs(
+    target_dirs: impl IntoIterator<Item = (Project, TargetDir)>,
+) -> Vec<(TargetDir, Vec<Project>)> {
+    let mut groups: Vec<(TargetDir, Vec<Project>)> = Vec::new();
+    for (project, target_dir) in target_dirs {
+        match groups.iter_mut().find(|(t, _)| t.path == target_dir.path) {
+            Some((t, projects)) => {
+                t.shared = true;
+                projects.push(project);
+            }
+            None => groups.push((target_dir, vec![project])),
+        }
+    }
+    groups
+}
+
+fn read_config_target_dir(config: &Path) -> Option<String> {
+    let contents = fs::read_to_string(config).ok()?;
+    let config = contents.parse::<toml::Table>().ok()?;
+    config
+        .get("build")?
+        .get("target-dir")?
+        .as_str()
+        .map(str::to_owned)
+}
+
+/// What [`sweep`] removes from a target directory
+#[derive(Debug, Clone)]
+pub struct SweepOptions {
+    /// Profiles such as `debug` or `x86_64-pc-windows-gnu/release` not built for this many
+    /// seconds are removed entirely
+    pub max_profile_age: u64,
+    /// Remove the `incremental` compilation caches of profiles that are kept
+    pub incremental: bool,
+    /// Remove artifacts built by toolchains that are no longer installed through rustup
+    pub uninstalled_toolchains: bool,
+    /// Where the target directory and the installed toolchains are looked up, this process'
+    /// environment by default
+    pub env: CargoEnv,
+}
+
+impl Default for SweepOptions {
+    fn default() -> Self {
+        Self {
+            max_profile_age: 30 * 24 * 60 * 60,
+            incremental: true,
+            uninstalled_toolchains: true,
+            env: CargoEnv::from_process(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepReason {
+    StaleProfile,
+    Incremental,
+    UninstalledToolchain,
+}
+
+impl SweepReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            SweepReason::StaleProfile => "profile not built recently",
+            SweepReason::Incremental => "incremental cache",
+            SweepReason::UninstalledToolchain => "built by an uninstalled toolchain",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SweepItem {
+    pub path: PathBuf,
+    pub size: u64,
+    pub reason: SweepReason,
+}
+
+/// Stale content found inside a Cargo target directory by [`sweep`]
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub target_dir: PathBuf,
+    /// Size of the whole target directory, what a full clean would reclaim
+    pub target_size: u64,
+    pub items: Vec<SweepItem>,
+}
+
+impl Sweep {
+    /// Bytes freed by [`Sweep::clean`]
+    pub fn reclaimable(&self) -> u64 {
+        self.items.iter().map(|i| i.size).sum()
+    }
+
+    /// Whether the target directory is in use, see [`TargetDir::in_use`]
+    pub fn in_use(&self) -> Option<InUse> {
+        self.in_use_with(&Processes::snapshot())
+    }
+
+    /// Like [`Sweep::in_use`], looking for processes in a snapshot taken beforehand
+    pub fn in_use_with(&self, processes: &Processes) -> Option<InUse> {
+        target_dir_in_use(&self.target_dir, processes)
+    }
+
+    /// Deletes the stale content, leaving the rest of the target directory intact. Nothing is
+    /// removed while the target directory is [in use](Sweep::in_use).
+    pub fn clean(&self) {
+        if let Err(in_use) = self.clean_with(&CleanOptions::default(), &Processes::snapshot()) {
+            eprintln!("not sweeping {:?}, it {in_use}", self.target_dir);
+        }
+    }
+
+    /// Like [`Sweep::clean`], returning why nothing was removed when the target directory is in
+    /// use. Only [`CleanOptions::allow_in_use`] applies.
+    pub fn clean_with(&self, options: &CleanOptions, processes: &Processes) -> Result<(), InUse> {
+        if !options.allow_in_use {
+            if let Some(in_use) = self.in_use_with(processes) {
+                return Err(in_use);
+            }
+        }
+        for item in &self.items {
+            let result = if item.path.is_dir() {
+                fs::remove_dir_all(&item.path)
+            } else {
+                fs::remove_file(&item.path)
+            };
+            if let Err(e) = result {
+                eprintln!("error removing {:?}: {:?}", item.path, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the stale content of the Cargo `target_dir`.
+///
+/// Cargo only records the toolchain it last ran with in `.rustc_info.json`, units are matched to
+/// it through the `rustc` hash in their fingerprint. When that toolchain is no longer installed
+/// everything is stale. Units built by a different toolchain are only considered stale when the
+/// last used toolchain is the only one installed, otherwise there's no telling them apart.
+pub fn sweep(target_dir: &Path, options: &SweepOptions) -> Sweep {
+    let target_size = crate::dir_size(&target_dir, &crate::ScanOptions::default());
+    let mut items = Vec::new();
+
+    let toolchains = if options.uninstalled_toolchains {
+        toolchain_status(target_dir, &options.env)
+    } else {
+        ToolchainStatus::Unknown
+    };
+
+    for profile in profile_dirs(target_dir) {
+        let units = fingerprint_units(&profile);
+
+        // Without any fingerprints there's no telling when the profile was built, so it's kept
+        let last_built = units.iter().filter_map(|u| u.modified).max();
+        let age = last_built.map(|t| t.elapsed().map(|d| d.as_secs()).unwrap_or(0));
+
+        let stale_reason = if age.is_some_and(|age| age >= options.max_profile_age) {
+            Some(SweepReason::StaleProfile)
+        } else if toolchains == ToolchainStatus::LastUsedUninstalled {
+            Some(SweepReason::UninstalledToolchain)
+        } else {
+            None
+        };
+        if let Some(reason) = stale_reason {
+            items.push(sweep_item(profile, reason));
+            continue;
+        }
+
+        let incremental = profile.join("incremental");
+        if options.incremental && incremental.is_dir() {
+            items.push(sweep_item(incremental, SweepReason::Incremental));
+        }
+
+        if toolchains == ToolchainStatus::OnlyLastUsedInstalled {
+            // The most recently built units belong to the last used toolchain
+            let current = units
+                .iter()
+                .filter(|u| u.rustc.is_some())
+                .max_by_key(|u| u.modified)
+                .and_then(|u| u.rustc);
+            for unit in units
+                .iter()
+                .filter(|u| u.rustc.is_some() && u.rustc != current)
+            {
+                items.extend(
+                    unit_paths(&profile, &unit.name)
+                        .into_iter()
+                        .map(|p| sweep_item(p, SweepReason::UninstalledToolchain)),
+                );
+            }
+        }
+    }
+
+    Sweep {
+        target_dir: target_dir.to_path_buf(),
+        target_size,
+        items,
+    }
+}
+
+fn sweep_item(path: PathBuf, reason: SweepReason) -> SweepItem {
+    let size = if path.is_dir() {
+        crate::dir_size(&path, &crate::ScanOptions::default())
+    } else {
+        fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+    };
+    SweepItem { path, size, reason }
+}
+
+/// Profile directories hold a `.fingerprint` directory, they're either directly in the target
+/// directory or under a target triple.
+fn profile_dirs(target_dir: &Path) -> Vec<PathBuf> {
+    let subdirs = |dir: &Path| -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|rd| {
+                rd.filter_map(|e| e.ok())
+                    .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+                    .map(|e| e.path())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut profiles = Vec::new();
+    for dir in subdirs(target_dir) {
+        if dir.join(".fingerprint").is_dir() {
+            profiles.push(dir);
+        } else {
+            profiles.extend(
+                subdirs(&dir)
+                    .into_iter()
+                    .filter(|d| d.join(".fingerprint").is_dir()),
+            );
+        }
+    }
+    profiles
+}
+
+struct FingerprintUnit {
+    /// `<crate name>-<metadata hash>`
+    name: String,
+    rustc: Option<u64>,
+    modified: Option<std::time::SystemTime>,
+}
+
+fn fingerprint_units(profile: &Path) -> Vec<FingerprintUnit> {
+    let Ok(rd) = fs::read_dir(profile.join(".fingerprint")) else {
+        return Vec::new();
+    };
+
+    rd.filter_map(|e| e.ok())
+        .filter_map(|unit| {
+            let name = unit.file_name().into_string().ok()?;
+            let mut rustc = None;
+            let mut modified = None;
+            for file in fs::read_dir(unit.path()).ok()?.filter_map(|e| e.ok()) {
+                let Ok(metadata) = file.metadata() else {
+                    continue;
+                };
+                if let Ok(m) = metadata.modified() {
+                    modified = modified.max(Some(m));
+                }
+                if rustc.is_none() && file.path().extension().is_some_and(|e| e == "json") {
+                    rustc = fs::read_to_string(file.path())
+                        .ok()
+                        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+                        .and_then(|v| v["rustc"].as_u64());
+                }
+            }
+            Some(FingerprintUnit {
+                name,
+                rustc,
+                modified,
+            })
+        })
+        .collect()
+}
+
+/// The fingerprint, build script output and `deps` files of a unit
+fn unit_paths(profile: &Path, unit: &str) -> Vec<PathBuf> {
+    let mut paths = vec![profile.join(".fingerprint").join(unit)];
+
+    let build = profile.join("build").join(unit);
+    if build.is_dir() {
+        paths.push(build);
+    }
+
+    // Files in `deps` use the crate name with underscores, e.g. `libserde_json-<hash>.rlib`
+    let deps_name = unit.replace('-', "_");
+    if let Ok(rd) = fs::read_dir(profile.join("deps")) {
+        paths.extend(rd.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| {
+            p.file_stem()
+                .and_then(|s| s.to_str())
+                .map(|s| s.replace('-', "_"))
+                .is_some_and(|s| s == deps_name || s.strip_prefix("lib") == Some(&deps_name))
+        }));
+    }
+
+    paths
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ToolchainStatus {
+    Unknown,
+    LastUsedUninstalled,
+    OnlyLastUsedInstalled,
+    Installed,
+}
+
+fn toolchain_status(target_dir: &Path, env: &CargoEnv) -> ToolchainStatus {
+    let Some(rustup_home) = env.home_dir("RUSTUP_HOME", ".rustup") else {
+        return ToolchainStatus::Unknown;
+    };
+    let Some(LastUsedRustc {
+        version: last_used,
+        sysroot,
+    }) = last_used_rustc(target_dir)
+    else {
+        return ToolchainStatus::Unknown;
+    };
+    // A rustc that rustup doesn't manage, like a distribution's, is never among the installed
+    // toolchains
+    if !sysroot.starts_with(rustup_home.join("toolchains")) {
+        return ToolchainStatus::Unknown;
+    }
+    let installed = installed_rustc_versions(&rustup_home);
+    if installed.is_empty() {
+        return ToolchainStatus::Unknown;
+    }
+
+    if !installed.contains(&last_used) {
+        ToolchainStatus::LastUsedUninstalled
+    } else if installed.iter().all(|v| *v == last_used) {
+        ToolchainStatus::OnlyLastUsedInstalled
+    } else {
+        ToolchainStatus::Installed
+    }
+}
+
+struct LastUsedRustc {
+    /// e.g. `1.76.0 (07dca489a 2024-02-04)`
+    version: String,
+    sysroot: PathBuf,
+}
+
+/// The rustc cargo last used with `target_dir`, from the output of `rustc -vV` and of the
+/// `--print=sysroot` query it caches in `.rustc_info.json`
+fn last_used_rustc(target_dir: &Path) -> Option<LastUsedRustc> {
+    let contents = fs::read_to_string(target_dir.join(".rustc_info.json")).ok()?;
+    let info: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let outputs: Vec<&str> = info["outputs"]
+        .as_object()?
+        .values()
+        .filter_map(|o| o["stdout"].as_str())
+        .collect();
+
+    let version = outputs.iter().find_map(|stdout| {
+        stdout
+            .lines()
+            .next()?
+            .strip_prefix("rustc ")
+            .map(str::to_owned)
+    })?;
+    // The only absolute path among the printed file names and cfgs
+    let sysroot = outputs
+        .iter()
+        .flat_map(|stdout| stdout.lines())
+        .map(Path::new)
+        .find(|line| line.is_absolute())?
+        .to_path_buf();
+
+    Some(LastUsedRustc { version, sysroot })
+}
+
+/// Versions of the toolchains installed through rustup, in the same format as
+/// [`LastUsedRustc::version`]
+fn installed_rustc_versions(rustup_home: &Path) -> Vec<String> {
+    let Ok(toolchains) = fs::read_dir(rustup_home.join("toolchains")) else {
+        return Vec::new();
+    };
+
+    toolchains
+        .filter_map(|e| e.ok())
+        .filter_map(|toolchain| {
+            let manifest = toolchain
+                .path()
+                .join("lib/rustlib/multirust-channel-manifest.toml");
+            let manifest = fs::read_to_string(manifest)
+                .ok()?
+                .parse::<toml::Table>()
+                .ok()?;
+            manifest
+                .get("pkg")?
+                .get("rustc")?
+                .get("version")?
+                .as_str()
+                .map(str::to_owned)
+        })
+        .collect()
+}
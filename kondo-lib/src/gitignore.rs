@@ -0,0 +1,216 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{detect_project_type, dir_size, ScanOptions};
+
+const DIR_GIT: &str = ".git";
+
+/// Directories in the git repository at `repo` that are ignored through its `.gitignore` files
+/// and hold no tracked files, at least `min_size` bytes large. Only the topmost ignored
+/// directory of a tree is reported, and nested projects and repositories are left to be reported
+/// on their own. The user's global excludes and `.git/info/exclude` aren't part of the repository
+/// and don't count.
+pub(crate) fn ignored_dirs(repo: &Path, min_size: u64, options: &ScanOptions) -> Vec<PathBuf> {
+    // Without knowing what's tracked nothing can be considered an artifact
+    let tracked = match tracked_paths(repo) {
+        Ok(tracked) => tracked,
+        Err(e) => {
+            eprintln!("error reading the git index of {repo:?}: {e}");
+            return Vec::new();
+        }
+    };
+
+    let root = repo.to_path_buf();
+    let visited: HashSet<PathBuf> = ignore::WalkBuilder::new(repo)
+        .hidden(false)
+        .ignore(false)
+        .parents(false)
+        .git_global(false)
+        .git_exclude(false)
+        .follow_links(options.follow_symlinks)
+        .same_file_system(options.same_file_system)
+        .filter_entry(move |e| {
+            e.file_type().is_some_and(|t| t.is_dir())
+                && e.file_name() != DIR_GIT
+                && (e.path() == root
+                    || (detect_project_type(e.path()).is_none()
+                        && !e.path().join(DIR_GIT).exists()))
+        })
+        .build()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .collect();
+
+    let mut dirs: Vec<PathBuf> = visited
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|rd| rd.filter_map(|rd| rd.ok()))
+        .filter(|de| de.file_type().is_ok_and(|t| t.is_dir()) && de.file_name() != DIR_GIT)
+        .map(|de| de.path())
+        .filter(|p| !visited.contains(p) && !tracked.contains(p))
+        // Skipped by the walk for being a project rather than for being ignored
+        .filter(|p| detect_project_type(p).is_none() && !p.join(DIR_GIT).exists())
+        .filter(|p| dir_size(p, options) >= min_size)
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Every file tracked in the git index of `repo` and the directories holding them
+fn tracked_paths(repo: &Path) -> io::Result<HashSet<PathBuf>> {
+    let git_dir = git_dir(repo)?;
+    let index = match fs::read(git_dir.join("index")) {
+        Ok(index) => index,
+        // Nothing has been added yet
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut paths = HashSet::new();
+    for path in read_index(&index, hash_len(&git_dir))? {
+        // Submodules and sparse checkouts have entries for directories, so the entry itself is
+        // included
+        let mut path = repo.join(path.trim_end_matches('/'));
+        while path != repo && paths.insert(path.clone()) {
+            if !path.pop() {
+                break;
+            }
+        }
+    }
+    Ok(paths)
+}
+
+/// The `.git` directory, following the `gitdir:` file worktrees and submodules have instead
+fn git_dir(repo: &Path) -> io::Result<PathBuf> {
+    let dot_git = repo.join(DIR_GIT);
+    if dot_git.is_dir() {
+        return Ok(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git)?;
+    match contents.trim().strip_prefix("gitdir:") {
+        Some(git_dir) => Ok(repo.join(git_dir.trim())),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{dot_git:?} isn't a gitdir file"),
+        )),
+    }
+}
+
+fn hash_len(git_dir: &Path) -> usize {
+    let sha256 = fs::read_to_string(git_dir.join("config")).is_ok_and(|config| {
+        config.lines().any(|line| {
+            line.split_once('=').is_some_and(|(key, value)| {
+                key.trim().eq_ignore_ascii_case("objectformat") && value.trim() == "sha256"
+            })
+        })
+    });
+    if sha256 {
+        32
+    } else {
+        20
+    }
+}
+
+/// Paths of the entries in a git index file, versions 2 to 4. Split and sparse indexes, where
+/// the entries don't list every tracked file, are an error.
+/// See https://git-scm.com/docs/index-format
+fn read_index(index: &[u8], hash_len: usize) -> io::Result<Vec<String>> {
+    // ctime, mtime, dev, ino, mode, uid, gid and size fields before the object name
+    const STAT_LEN: usize = 40;
+    const FLAG_EXTENDED: u16 = 0x4000;
+
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let read_u32 = |pos: usize| {
+        index
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid("truncated index"))
+    };
+
+    if index.get(..4) != Some(b"DIRC") {
+        return Err(invalid("not a git index"));
+    }
+    let version = read_u32(4)?;
+    if !(2..=4).contains(&version) {
+        return Err(invalid("unsupported git index version"));
+    }
+    let entries = read_u32(8)? as usize;
+
+    let mut paths = Vec::with_capacity(entries);
+    let mut previous: Vec<u8> = Vec::new();
+    let mut pos = 12;
+    for _ in 0..entries {
+        let flags_pos = pos + STAT_LEN + hash_len;
+        let flags = index
+            .get(flags_pos..flags_pos + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| invalid("truncated index"))?;
+        let mut path_pos = flags_pos + 2;
+        if version >= 3 && flags & FLAG_EXTENDED != 0 {
+            path_pos += 2;
+        }
+
+        // Version 4 only stores what differs from the previous entry's path
+        let mut path = Vec::new();
+        let mut name_pos = path_pos;
+        if version == 4 {
+            let (strip, len) = read_offset(index.get(path_pos..).unwrap_or_default())
+                .ok_or_else(|| invalid("truncated index"))?;
+            let keep = previous
+                .len()
+                .checked_sub(strip)
+                .ok_or_else(|| invalid("invalid path prefix"))?;
+            path.extend_from_slice(&previous[..keep]);
+            name_pos += len;
+        }
+        let name_len = index
+            .get(name_pos..)
+            .and_then(|rest| rest.iter().position(|b| *b == 0))
+            .ok_or_else(|| invalid("truncated index"))?;
+        path.extend_from_slice(&index[name_pos..name_pos + name_len]);
+
+        pos = if version == 4 {
+            name_pos + name_len + 1
+        } else {
+            // Entries are padded with NULs to a multiple of eight bytes
+            let entry_len = name_pos + name_len - pos;
+            pos + (entry_len + 8) / 8 * 8
+        };
+
+        paths.push(String::from_utf8_lossy(&path).into_owned());
+        previous = path;
+    }
+
+    // Extensions follow the entries, up to the checksum at the end. Some of them mean the
+    // entries aren't the whole of what's tracked.
+    let end = index.len().saturating_sub(hash_len);
+    while pos + 8 <= end {
+        match &index[pos..pos + 4] {
+            b"link" => return Err(invalid("split git indexes aren't supported")),
+            b"sdir" => return Err(invalid("sparse git indexes aren't supported")),
+            _ => pos += 8 + read_u32(pos + 4)? as usize,
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Reads git's offset encoding of a variable length integer, returning it with the number of
+/// bytes it took up
+fn read_offset(bytes: &[u8]) -> Option<(usize, usize)> {
+    let mut bytes = bytes.iter().enumerate();
+    let (_, first) = bytes.next()?;
+    let mut value = (first & 0x7f) as usize;
+    let mut more = first & 0x80 != 0;
+    let mut len = 1;
+    while more {
+        let (i, byte) = bytes.next()?;
+        value = ((value + 1) << 7) | (byte & 0x7f) as usize;
+        more = byte & 0x80 != 0;
+        len = i + 1;
+    }
+    Some((value, len))
+}
@@ -0,0 +1,213 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+// Lock files build tools hold while building, by the name of the artifact directory they're in.
+// A leading `*` matches any prefix.
+const BUILD_LOCKS: [(&str, &str, LockKind); 2] = [
+    ("target", ".cargo-lock", LockKind::Flock),
+    (".gradle", "*.lock", LockKind::Record),
+];
+// Deep enough for `target/<triple>/debug/.cargo-lock`
+const BUILD_LOCK_DEPTH: usize = 3;
+
+/// Why a project shouldn't be cleaned right now, see [`Project::in_use`](crate::Project::in_use)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InUse {
+    /// A build tool holds the lock file at the given path
+    BuildLock(PathBuf),
+    /// A process is running inside the project or has a file in it open
+    Process {
+        pid: u32,
+        name: String,
+        /// The process' working directory or the open file
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for InUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InUse::BuildLock(lock) => {
+                write!(f, "is being built, {} is locked", lock.to_string_lossy())
+            }
+            InUse::Process { pid, name, path } => write!(
+                f,
+                "is in use by {name} (pid {pid}) through {}",
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+/// How a build tool locks its lock file, the two kinds don't see each other on Unix
+#[derive(Debug, Clone, Copy)]
+enum LockKind {
+    /// `flock`, as Cargo does
+    Flock,
+    /// `fcntl` record locks, which Java's `FileChannel.lock` takes
+    Record,
+}
+
+/// A build lock inside `artifact_dir` that another process holds
+pub(crate) fn held_build_lock(artifact_dir: &Path) -> Option<PathBuf> {
+    let name = artifact_dir.file_name()?.to_str()?;
+    let (_, lock_name, kind) = BUILD_LOCKS.iter().find(|(dir, _, _)| *dir == name)?;
+    held_lock(artifact_dir, lock_name, *kind)
+}
+
+/// The build lock inside the Cargo `target_dir` that another process holds, whatever the
+/// directory is called
+pub(crate) fn held_cargo_lock(target_dir: &Path) -> Option<PathBuf> {
+    let (_, lock_name, kind) = BUILD_LOCKS[0];
+    held_lock(target_dir, lock_name, kind)
+}
+
+fn held_lock(dir: &Path, lock_name: &str, kind: LockKind) -> Option<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .max_depth(BUILD_LOCK_DEPTH)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            let file_name = e.file_name().to_string_lossy();
+            match lock_name.strip_prefix('*') {
+                Some(suffix) => file_name.ends_with(suffix),
+                None => file_name == lock_name,
+            }
+        })
+        .map(|e| e.into_path())
+        .find(|lock| is_locked(lock, kind))
+}
+
+fn is_locked(path: &Path, kind: LockKind) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    match kind {
+        #[cfg(unix)]
+        LockKind::Record => has_record_lock(&file),
+        // The lock is released again when the file is closed. On Windows both kinds of locks
+        // are taken through `LockFileEx`.
+        _ => matches!(file.try_lock(), Err(fs::TryLockError::WouldBlock)),
+    }
+}
+
+/// Whether a write lock on the whole of `file` would conflict with a record lock, asked without
+/// taking one
+#[cfg(unix)]
+fn has_record_lock(file: &fs::File) -> bool {
+    use std::os::fd::AsRawFd;
+
+    // Open file description locks conflict with the process' own locks too, unlike `F_GETLK`
+    #[cfg(target_os = "linux")]
+    const GET_LOCK: libc::c_int = libc::F_OFD_GETLK;
+    #[cfg(not(target_os = "linux"))]
+    const GET_LOCK: libc::c_int = libc::F_GETLK;
+
+    // SAFETY: `flock` is plain data, all zeroes is a valid value and the zero `l_pid` is what
+    // `F_OFD_GETLK` requires
+    let mut lock: libc::flock = unsafe { std::mem::zeroed() };
+    lock.l_type = libc::F_WRLCK as libc::c_short;
+    lock.l_whence = libc::SEEK_SET as libc::c_short;
+    // SAFETY: the descriptor stays open for the call, and `lock` outlives it
+    let result = unsafe { libc::fcntl(file.as_raw_fd(), GET_LOCK, &mut lock) };
+    result == 0 && lock.l_type != libc::F_UNLCK as libc::c_short
+}
+
+/// The working directories and open files of the running processes, as they were when the
+/// snapshot was taken. This process and the ones that started it aren't included. Checking many
+/// projects against one snapshot saves going through every process for each of them. Processes
+/// are only found on Linux, elsewhere the snapshot is empty.
+#[derive(Debug, Clone, Default)]
+pub struct Processes(Vec<ProcessPaths>);
+
+#[derive(Debug, Clone)]
+struct ProcessPaths {
+    pid: u32,
+    name: String,
+    cwd: Option<PathBuf>,
+    open_files: Vec<PathBuf>,
+}
+
+impl Processes {
+    #[cfg(target_os = "linux")]
+    pub fn snapshot() -> Self {
+        // The shell kondo was started from is likely to be inside the project
+        let own_processes = own_process_tree();
+        let Ok(proc) = fs::read_dir("/proc") else {
+            return Self::default();
+        };
+
+        let processes = proc
+            .filter_map(|e| e.ok())
+            .filter_map(|entry| {
+                let pid = entry.file_name().to_str()?.parse().ok()?;
+                if own_processes.contains(&pid) {
+                    return None;
+                }
+                let proc_dir = entry.path();
+                let name = fs::read_to_string(proc_dir.join("comm")).unwrap_or_default();
+                let open_files = fs::read_dir(proc_dir.join("fd"))
+                    .map(|fds| {
+                        fds.filter_map(|fd| fd.ok())
+                            .filter_map(|fd| fs::read_link(fd.path()).ok())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Some(ProcessPaths {
+                    pid,
+                    name: name.trim().to_string(),
+                    cwd: fs::read_link(proc_dir.join("cwd")).ok(),
+                    open_files,
+                })
+            })
+            .collect();
+        Self(processes)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn snapshot() -> Self {
+        Self::default()
+    }
+
+    /// A process with its working directory or an open file inside `dir`
+    pub(crate) fn using(&self, dir: &Path) -> Option<InUse> {
+        let dir = fs::canonicalize(dir).ok()?;
+        self.0.iter().find_map(|process| {
+            let path = process
+                .cwd
+                .iter()
+                .chain(&process.open_files)
+                .find(|path| path.starts_with(&dir))?;
+            Some(InUse::Process {
+                pid: process.pid,
+                name: process.name.clone(),
+                path: path.clone(),
+            })
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn own_process_tree() -> Vec<u32> {
+    let mut pids = vec![std::process::id()];
+    while let Some(parent) = parent_pid(pids[pids.len() - 1]) {
+        // pid 1 is init, and the kernel's threads have 0 as their parent
+        if parent <= 1 || pids.contains(&parent) {
+            break;
+        }
+        pids.push(parent);
+    }
+    pids
+}
+
+/// Reads the parent from `/proc/<pid>/stat`, which looks like `1234 (name) S 1200 ...`. The name
+/// can contain spaces and parentheses itself.
+#[cfg(target_os = "linux")]
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(1)?.parse().ok()
+}
@@ -1,11 +1,26 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
+    env,
     error::{self, Error},
-    fs,
+    fmt, fs,
+    io::Read,
     path::{self, Path},
     time::SystemTime,
 };
 
+mod cargo;
+mod gitignore;
+mod in_use;
+mod mounts;
+mod nix;
+
+pub use cargo::{
+    group_target_dirs, sweep, CargoEnv, Sweep, SweepItem, SweepOptions, SweepReason, TargetDir,
+};
+pub use in_use::{InUse, Processes};
+pub use mounts::{mount_of, mounts, Mount, DEFAULT_SKIPPED_FILESYSTEMS};
+
 const FILE_CARGO_TOML: &str = "Cargo.toml";
 const FILE_PACKAGE_JSON: &str = "package.json";
 const FILE_ASSEMBLY_CSHARP: &str = "Assembly-CSharp.csproj";
@@ -16,26 +31,86 @@ const FILE_MVN_BUILD: &str = "pom.xml";
 const FILE_BUILD_GRADLE: &str = "build.gradle";
 const FILE_BUILD_GRADLE_KTS: &str = "build.gradle.kts";
 const FILE_CMAKE_BUILD: &str = "CMakeLists.txt";
+const FILE_MESON_BUILD: &str = "meson.build";
+const FILE_AUTOCONF: &str = "configure.ac";
+const FILE_AUTOCONF_LEGACY: &str = "configure.in";
+const FILE_CMAKE_CACHE: &str = "CMakeCache.txt";
+const FILE_AUTOCONF_STATUS: &str = "config.status";
+const FILE_NINJA_BUILD: &str = "build.ninja";
+const FILE_NINJA_LOG: &str = ".ninja_log";
+const DIR_MESON_PRIVATE: &str = "meson-private";
+const FILE_BAZEL_MODULE: &str = "MODULE.bazel";
+const FILE_BAZEL_WORKSPACE: &str = "WORKSPACE";
+const FILE_BAZEL_WORKSPACE_BAZEL: &str = "WORKSPACE.bazel";
+const FILE_BUCKCONFIG: &str = ".buckconfig";
+const FILE_PANTS_TOML: &str = "pants.toml";
+const FILE_NIX_FLAKE: &str = "flake.nix";
+const FILE_NIX_SHELL: &str = "shell.nix";
+const FILE_NIX_DEFAULT: &str = "default.nix";
+const FILE_ENVRC: &str = ".envrc";
+const DIR_NIX_STORE: &str = "/nix/store";
+const FILE_GEMFILE: &str = "Gemfile";
+const FILE_GO_MOD: &str = "go.mod";
+const FILE_DUNE_PROJECT: &str = "dune-project";
+const FILE_REBAR_CONFIG: &str = "rebar.config";
+const FILE_JULIA_PROJECT: &str = "Project.toml";
+const FILE_RENV_LOCK: &str = "renv.lock";
+const FILE_LATEXMKRC: &str = ".latexmkrc";
+const FILE_LATEX_SUFFIX: &str = ".tex";
 const FILE_UNREAL_SUFFIX: &str = ".uproject";
 const FILE_JUPYTER_SUFFIX: &str = ".ipynb";
 const FILE_PYTHON_SUFFIX: &str = ".py";
+const FILE_PYPROJECT_TOML: &str = "pyproject.toml";
+const FILE_SETUP_PY: &str = "setup.py";
+const FILE_SETUP_CFG: &str = "setup.cfg";
+const FILE_PIPFILE: &str = "Pipfile";
+const FILE_UV_LOCK: &str = "uv.lock";
+const FILE_REQUIREMENTS_PREFIX: &str = "requirements";
+const FILE_PYVENV_CFG: &str = "pyvenv.cfg";
 const FILE_PIXI_PACKAGE: &str = "pixi.toml";
 const FILE_COMPOSER_JSON: &str = "composer.json";
 const FILE_PUBSPEC_YAML: &str = "pubspec.yaml";
 const FILE_ELIXIR_MIX: &str = "mix.exs";
 const FILE_SWIFT_PACKAGE: &str = "Package.swift";
 const FILE_BUILD_ZIG: &str = "build.zig";
-const FILE_GODOT_4_PROJECT: &str = "project.godot";
+const FILE_GODOT_PROJECT: &str = "project.godot";
+const FILE_UNITY_PROJECT_VERSION: &str = "ProjectSettings/ProjectVersion.txt";
+const DIR_UNITY_ASSETS: &str = "Assets";
 const FILE_CSPROJ_SUFFIX: &str = ".csproj";
 const FILE_FSPROJ_SUFFIX: &str = ".fsproj";
 const FILE_TERRAFORM_HCL: &str = ".terraform.lock.hcl";
 const FILE_PROJECT_TURBOREPO: &str = "turbo.json";
 const FILE_PODFILE: &str = "Podfile";
+const FILE_PNPM_WORKSPACE: &str = "pnpm-workspace.yaml";
+const FILE_PNPM_LOCK: &str = "pnpm-lock.yaml";
+const FILE_YARN_LOCK: &str = "yarn.lock";
+const FILE_YARNRC_YML: &str = ".yarnrc.yml";
+const FILE_SETTINGS_GRADLE: &str = "settings.gradle";
+const FILE_SETTINGS_GRADLE_KTS: &str = "settings.gradle.kts";
+const FILE_CACHEDIR_TAG: &str = "CACHEDIR.TAG";
+// https://bford.info/cachedir/
+const CACHEDIR_TAG_SIGNATURE: &[u8] = b"Signature: 8a477f597d28d172789f06886806bc55";
 
 const PROJECT_CARGO_DIRS: [&str; 2] = ["target", ".xwin-cache"];
-const PROJECT_NODE_DIRS: [&str; 2] = ["node_modules", ".angular"];
+const PROJECT_NODE_DIRS: [&str; 2] = ["**/node_modules", ".angular"];
+// Framework and tool caches, offered when the tool is one of the project's dependencies
+const PROJECT_NODE_DEPENDENCY_DIRS: [(&str, &[&str]); 10] = [
+    ("next", &[".next"]),
+    ("nuxt", &[".nuxt", ".output"]),
+    ("@sveltejs/kit", &[".svelte-kit"]),
+    ("parcel", &[".parcel-cache"]),
+    ("parcel-bundler", &[".cache"]),
+    ("vite", &[".vite"]),
+    ("gatsby", &[".cache"]),
+    ("@docusaurus/core", &[".docusaurus"]),
+    ("storybook", &["storybook-static"]),
+    ("@storybook/", &["storybook-static"]),
+];
+// Yarn Berry, as opposed to Yarn 1 which doesn't have a .yarnrc.yml
+const PROJECT_YARN_BERRY_DIRS: [&str; 2] = [".yarn/cache", ".yarn/unplugged"];
+const PROJECT_PNPM_DIRS: [&str; 1] = [".pnpm-store"];
 const PROJECT_REACT_NATIVE_DIRS: [&str; 8] = [
-    "node_modules",
+    "**/node_modules",
     "android/build",
     "android/.gradle",
     "ios/build",
@@ -59,6 +134,37 @@ const PROJECT_SBT_DIRS: [&str; 2] = ["target", "project/target"];
 const PROJECT_MVN_DIRS: [&str; 1] = ["target"];
 const PROJECT_GRADLE_DIRS: [&str; 2] = ["build", ".gradle"];
 const PROJECT_CMAKE_DIRS: [&str; 3] = ["build", "cmake-build-debug", "cmake-build-release"];
+const PROJECT_MESON_DIRS: [&str; 1] = ["subprojects/packagecache"];
+const PROJECT_AUTOTOOLS_DIRS: [&str; 1] = ["autom4te.cache"];
+// The output base lives outside the workspace, see `bazel_output_base`
+const PROJECT_BAZEL_DIRS: [&str; 0] = [];
+const PROJECT_BUCK2_DIRS: [&str; 1] = ["buck-out"];
+const PROJECT_PANTS_DIRS: [&str; 1] = [".pants.d"];
+// Added to any project using Nix or direnv, see `nix_artifact_paths`
+const PROJECT_NIX_DIRS: [&str; 0] = [];
+const PROJECT_RUBY_DIRS: [&str; 3] = ["vendor/bundle", ".bundle", "tmp/cache"];
+const PROJECT_GO_DIRS: [&str; 2] = ["vendor", "bin"];
+const PROJECT_DUNE_DIRS: [&str; 2] = ["_build", "_opam"];
+const PROJECT_REBAR3_DIRS: [&str; 1] = ["_build"];
+const PROJECT_JULIA_DIRS: [&str; 1] = ["docs/build"];
+const PROJECT_RENV_DIRS: [&str; 1] = ["renv/library"];
+// The intermediate files are matched to documents, see `latex_output_files`
+const PROJECT_LATEX_DIRS: [&str; 1] = ["_minted-*"];
+const LATEX_OUTPUT_EXTENSIONS: [&str; 13] = [
+    "aux",
+    "bbl",
+    "bcf",
+    "blg",
+    "fdb_latexmk",
+    "fls",
+    "lof",
+    "log",
+    "lot",
+    "out",
+    "run.xml",
+    "synctex.gz",
+    "toc",
+];
 const PROJECT_UNREAL_DIRS: [&str; 5] = [
     "Binaries",
     "Build",
@@ -66,15 +172,29 @@ const PROJECT_UNREAL_DIRS: [&str; 5] = [
     "DerivedDataCache",
     "Intermediate",
 ];
-const PROJECT_JUPYTER_DIRS: [&str; 1] = [".ipynb_checkpoints"];
-const PROJECT_PYTHON_DIRS: [&str; 7] = [
+const PROJECT_JUPYTER_DIRS: [&str; 1] = ["**/.ipynb_checkpoints"];
+const PROJECT_PYTHON_DIRS: [&str; 13] = [
     ".mypy_cache",
     ".nox",
-    ".pytest_cache",
+    "**/.pytest_cache",
     ".ruff_cache",
     ".tox",
-    "__pycache__",
+    "**/__pycache__",
     "__pypackages__",
+    "**/.ipynb_checkpoints",
+    ".hypothesis",
+    ".eggs",
+    "build",
+    "dist",
+    "**/*.egg-info",
+];
+// Loose scripts without packaging metadata, where `build` or `dist` could be anything
+const PROJECT_PYTHON_SCRIPTS_DIRS: [&str; 5] = [
+    ".mypy_cache",
+    "**/.pytest_cache",
+    ".ruff_cache",
+    "**/__pycache__",
+    "**/.ipynb_checkpoints",
 ];
 const PROJECT_PIXI_DIRS: [&str; 1] = [".pixi"];
 const PROJECT_COMPOSER_DIRS: [&str; 1] = ["vendor"];
@@ -87,11 +207,136 @@ const PROJECT_PUB_DIRS: [&str; 4] = [
 const PROJECT_ELIXIR_DIRS: [&str; 4] = ["_build", ".elixir-tools", ".elixir_ls", ".lexical"];
 const PROJECT_SWIFT_DIRS: [&str; 2] = [".build", ".swiftpm"];
 const PROJECT_ZIG_DIRS: [&str; 3] = ["zig-cache", ".zig-cache", "zig-out"];
+const PROJECT_GODOT_3_DIRS: [&str; 1] = [".import"];
 const PROJECT_GODOT_4_DIRS: [&str; 1] = [".godot"];
 const PROJECT_DOTNET_DIRS: [&str; 2] = ["bin", "obj"];
 const PROJECT_TURBOREPO_DIRS: [&str; 1] = [".turbo"];
 const PROJECT_TERRAFORM_DIRS: [&str; 1] = [".terraform"];
 const PROJECT_COCOAPODS_DIRS: [&str; 1] = ["Pods"];
+// Found through the repository's .gitignore files, see `ScanOptions::gitignored_min_size`
+const PROJECT_GITIGNORED_DIRS: [&str; 0] = [];
+
+// Directories that are still skipped when `ScanOptions::include_hidden` is set, a trailing `*`
+// matches any suffix and entries with a `/` are matched against the end of the path. Package
+// registries and toolchains in the home directory are full of sources that look like projects.
+const SKIPPED_HIDDEN_DIRS: [&str; 12] = [
+    ".git",
+    ".hg",
+    ".svn",
+    ".cache",
+    ".Trash",
+    ".Trash-*",
+    ".local/share/Trash",
+    ".cargo",
+    ".rustup",
+    ".npm",
+    ".m2",
+    ".gradle",
+];
+
+// Artifact directory names that are just as likely to hold sources, these need evidence from
+// `ARTIFACT_MARKERS` before they're considered verified
+const GENERIC_ARTIFACT_DIRS: [&str; 12] = [
+    "bin", "Binaries", "build", "Build", "Builds", "dist", "Logs", "obj", "Obj", "out", "target",
+    "vendor",
+];
+// Entries a build tool leaves in its artifact directories, `*` patterns are matched against file
+// names a few levels down
+const ARTIFACT_MARKERS: [(ProjectType, &str, &[&str]); 19] = [
+    (
+        ProjectType::Cargo,
+        "target",
+        &[".rustc_info.json", "CACHEDIR.TAG"],
+    ),
+    (
+        ProjectType::Node,
+        "node_modules",
+        &[
+            ".bin",
+            ".package-lock.json",
+            ".modules.yaml",
+            ".yarn-state.yml",
+            ".yarn-integrity",
+        ],
+    ),
+    (
+        ProjectType::Maven,
+        "target",
+        &["maven-status", "maven-archiver", "classes"],
+    ),
+    (
+        ProjectType::SBT,
+        "target",
+        &["streams", "scala-*", "task-temp-directory"],
+    ),
+    (
+        ProjectType::CMake,
+        "build",
+        &["CMakeCache.txt", "CMakeFiles"],
+    ),
+    (
+        ProjectType::Gradle,
+        "build",
+        &[
+            "tmp",
+            "classes",
+            "libs",
+            "kotlin",
+            "intermediates",
+            "generated",
+        ],
+    ),
+    (ProjectType::Dotnet, "bin", &["*.dll", "*.exe", "*.pdb"]),
+    (
+        ProjectType::Dotnet,
+        "obj",
+        &["project.assets.json", "*.nuget.*"],
+    ),
+    (
+        ProjectType::Composer,
+        "vendor",
+        &["composer/installed.json"],
+    ),
+    (ProjectType::Go, "vendor", &["modules.txt"]),
+    (
+        ProjectType::Python,
+        "build",
+        &["lib", "lib.*", "bdist.*", "temp.*"],
+    ),
+    (ProjectType::Python, "dist", &["*.whl", "*.tar.gz"]),
+    (
+        ProjectType::Unity,
+        "Library",
+        &[
+            "ArtifactDB",
+            "SourceAssetDB",
+            "PackageCache",
+            "ScriptAssemblies",
+        ],
+    ),
+    (ProjectType::Unity, "Logs", &["*.log"]),
+    (
+        ProjectType::Unity,
+        "Build",
+        &["*_Data", "*.app", "*.apk", "*.aab"],
+    ),
+    (
+        ProjectType::Unity,
+        "Builds",
+        &["*_Data", "*.app", "*.apk", "*.aab"],
+    ),
+    (
+        ProjectType::Unreal,
+        "Binaries",
+        &["*.dll", "*.so", "*.dylib", "*.exe", "*.target", "*.modules"],
+    ),
+    (
+        ProjectType::Pub,
+        "build",
+        &["flutter_assets", "*.dill", "native_assets"],
+    ),
+    (ProjectType::Julia, "build", &["search_index.js"]),
+];
 
 const PROJECT_CARGO_NAME: &str = "Cargo";
 const PROJECT_NODE_NAME: &str = "Node";
@@ -103,22 +348,38 @@ const PROJECT_SBT_NAME: &str = "SBT";
 const PROJECT_MVN_NAME: &str = "Maven";
 const PROJECT_GRADLE_NAME: &str = "Gradle";
 const PROJECT_CMAKE_NAME: &str = "CMake";
+const PROJECT_MESON_NAME: &str = "Meson";
+const PROJECT_AUTOTOOLS_NAME: &str = "Autotools";
+const PROJECT_BAZEL_NAME: &str = "Bazel";
+const PROJECT_BUCK2_NAME: &str = "Buck2";
+const PROJECT_PANTS_NAME: &str = "Pants";
+const PROJECT_NIX_NAME: &str = "Nix";
+const PROJECT_RUBY_NAME: &str = "Ruby";
+const PROJECT_GO_NAME: &str = "Go";
+const PROJECT_DUNE_NAME: &str = "Dune";
+const PROJECT_REBAR3_NAME: &str = "Rebar3";
+const PROJECT_JULIA_NAME: &str = "Julia";
+const PROJECT_RENV_NAME: &str = "renv";
+const PROJECT_LATEX_NAME: &str = "LaTeX";
 const PROJECT_UNREAL_NAME: &str = "Unreal";
 const PROJECT_JUPYTER_NAME: &str = "Jupyter";
 const PROJECT_PYTHON_NAME: &str = "Python";
+const PROJECT_PYTHON_SCRIPTS_NAME: &str = "Python (scripts)";
 const PROJECT_PIXI_NAME: &str = "Pixi";
 const PROJECT_COMPOSER_NAME: &str = "Composer";
 const PROJECT_PUB_NAME: &str = "Pub";
 const PROJECT_ELIXIR_NAME: &str = "Elixir";
 const PROJECT_SWIFT_NAME: &str = "Swift";
 const PROJECT_ZIG_NAME: &str = "Zig";
+const PROJECT_GODOT_3_NAME: &str = "Godot 3.x";
 const PROJECT_GODOT_4_NAME: &str = "Godot 4.x";
 const PROJECT_DOTNET_NAME: &str = ".NET";
 const PROJECT_TURBOREPO_NAME: &str = "Turborepo";
 const PROJECT_TERRAFORM_NAME: &str = "Terraform";
 const PROJECT_COCOAPODS_NAME: &str = "CocoaPods";
+const PROJECT_GITIGNORED_NAME: &str = "Git (gitignore heuristic)";
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ProjectType {
     Cargo,
     Node,
@@ -130,6 +391,19 @@ pub enum ProjectType {
     Maven,
     Gradle,
     CMake,
+    Meson,
+    Autotools,
+    Bazel,
+    Buck2,
+    Pants,
+    Nix,
+    Ruby,
+    Go,
+    Dune,
+    Rebar3,
+    Julia,
+    Renv,
+    Latex,
     Unreal,
     Jupyter,
     Python,
@@ -139,11 +413,15 @@ pub enum ProjectType {
     Elixir,
     Swift,
     Zig,
+    Godot3,
     Godot4,
     Dotnet,
     Turborepo,
     Terraform,
     Cocoapods,
+    /// A git repository that isn't any other type of project, its artifacts are guessed from
+    /// what it ignores
+    Gitignored,
 }
 
 #[derive(Debug, Clone)]
@@ -157,11 +435,116 @@ pub struct ProjectSize {
     pub artifact_size: u64,
     pub non_artifact_size: u64,
     pub dirs: Vec<(String, u64, bool)>,
+    /// Every artifact directory found, relative to the project root
+    pub artifact_dirs: Vec<(String, u64)>,
+}
+
+/// How [`Project::clean_with`] treats artifact directories that look suspicious
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CleanOptions {
+    /// Leave artifact directories that couldn't be [verified](Project::is_verified_artifact)
+    pub verified_only: bool,
+    /// Also remove artifact directories that have a [`CleanRefusal`]
+    pub allow_nested: bool,
+    /// Also remove the target of a symlinked artifact unless it has a [`LinkTargetRefusal`], and
+    /// artifacts with filesystems mounted inside, rather than only unlinking the symlink and
+    /// leaving those artifacts alone. Symlinks inside artifacts are never followed.
+    pub remove_link_targets: bool,
+    /// Clean the project even when [`Project::in_use`] finds it in use
+    pub allow_in_use: bool,
+}
+
+/// Why an artifact directory isn't removed unless [`CleanOptions::allow_nested`] is set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CleanRefusal {
+    /// A version control checkout such as a `.git` directory was found inside
+    NestedRepository(path::PathBuf),
+    /// The directory is the root of another project
+    NestedProject(&'static str),
+}
+
+/// How an artifact path leads outside of its project, see [`Project::artifact_escape`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactEscape {
+    /// The artifact is a symlink to the given path
+    Symlink(path::PathBuf),
+    /// A symlink inside the artifact, the first path, leads to the second
+    NestedSymlink(path::PathBuf, path::PathBuf),
+    /// Another filesystem, like a bind mount, is mounted at the given path inside the artifact
+    MountPoint(path::PathBuf),
+}
+
+/// Why the target of a symlinked artifact is kept even with [`CleanOptions::remove_link_targets`],
+/// see [`Project::link_target_refusal`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTargetRefusal {
+    /// The target is the filesystem root, or holds the home directory or the project itself
+    Protected,
+    /// The target has a [`CleanRefusal`]
+    Refused(CleanRefusal),
+    /// The target's contents don't look like what the build tool generates
+    Unverified,
+    /// Another filesystem is mounted at the given path inside the target
+    MountPoint(path::PathBuf),
+}
+
+impl fmt::Display for LinkTargetRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkTargetRefusal::Protected => {
+                write!(
+                    f,
+                    "is the filesystem root or holds the home directory or project"
+                )
+            }
+            LinkTargetRefusal::Refused(refusal) => write!(f, "{refusal}"),
+            LinkTargetRefusal::Unverified => write!(f, "couldn't be verified as an artifact"),
+            LinkTargetRefusal::MountPoint(path) => {
+                write!(f, "has a filesystem mounted at {}", path.to_string_lossy())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ArtifactEscape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactEscape::Symlink(target) => {
+                write!(f, "is a symlink to {}", target.to_string_lossy())
+            }
+            ArtifactEscape::NestedSymlink(link, target) => write!(
+                f,
+                "contains a symlink from {} to {}",
+                link.to_string_lossy(),
+                target.to_string_lossy()
+            ),
+            ArtifactEscape::MountPoint(path) => {
+                write!(f, "has a filesystem mounted at {}", path.to_string_lossy())
+            }
+        }
+    }
+}
+
+impl fmt::Display for CleanRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CleanRefusal::NestedRepository(path) => {
+                write!(f, "contains the repository {}", path.to_string_lossy())
+            }
+            CleanRefusal::NestedProject(type_name) => write!(f, "is a {type_name} project"),
+        }
+    }
+}
+
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
 }
 
 impl Project {
-    pub fn artifact_dirs(&self) -> &[&str] {
-        match self.project_type {
+    /// Artifact directory names or patterns relative to the project root. Patterns such as
+    /// `**/__pycache__` may match any number of directories, see [`Project::artifact_paths`].
+    pub fn artifact_dirs(&self) -> Vec<&'static str> {
+        let dirs: &[&str] = match self.project_type {
             ProjectType::Cargo => &PROJECT_CARGO_DIRS,
             ProjectType::Node => {
                 if is_react_native_project(&self.path) {
@@ -177,32 +560,287 @@ impl Project {
             ProjectType::Maven => &PROJECT_MVN_DIRS,
             ProjectType::Unreal => &PROJECT_UNREAL_DIRS,
             ProjectType::Jupyter => &PROJECT_JUPYTER_DIRS,
-            ProjectType::Python => &PROJECT_PYTHON_DIRS,
+            ProjectType::Python => {
+                if is_python_package(&self.path) {
+                    &PROJECT_PYTHON_DIRS
+                } else {
+                    &PROJECT_PYTHON_SCRIPTS_DIRS
+                }
+            }
             ProjectType::Pixi => &PROJECT_PIXI_DIRS,
             ProjectType::CMake => &PROJECT_CMAKE_DIRS,
+            ProjectType::Meson => &PROJECT_MESON_DIRS,
+            ProjectType::Autotools => &PROJECT_AUTOTOOLS_DIRS,
+            ProjectType::Bazel => &PROJECT_BAZEL_DIRS,
+            ProjectType::Buck2 => &PROJECT_BUCK2_DIRS,
+            ProjectType::Pants => &PROJECT_PANTS_DIRS,
+            ProjectType::Nix => &PROJECT_NIX_DIRS,
+            ProjectType::Ruby => &PROJECT_RUBY_DIRS,
+            ProjectType::Go => &PROJECT_GO_DIRS,
+            ProjectType::Dune => &PROJECT_DUNE_DIRS,
+            ProjectType::Rebar3 => &PROJECT_REBAR3_DIRS,
+            ProjectType::Julia => &PROJECT_JULIA_DIRS,
+            ProjectType::Renv => &PROJECT_RENV_DIRS,
+            ProjectType::Latex => &PROJECT_LATEX_DIRS,
             ProjectType::Composer => &PROJECT_COMPOSER_DIRS,
             ProjectType::Pub => &PROJECT_PUB_DIRS,
             ProjectType::Elixir => &PROJECT_ELIXIR_DIRS,
             ProjectType::Swift => &PROJECT_SWIFT_DIRS,
             ProjectType::Gradle => &PROJECT_GRADLE_DIRS,
             ProjectType::Zig => &PROJECT_ZIG_DIRS,
+            ProjectType::Godot3 => &PROJECT_GODOT_3_DIRS,
             ProjectType::Godot4 => &PROJECT_GODOT_4_DIRS,
             ProjectType::Dotnet => &PROJECT_DOTNET_DIRS,
             ProjectType::Turborepo => &PROJECT_TURBOREPO_DIRS,
             ProjectType::Terraform => &PROJECT_TERRAFORM_DIRS,
             ProjectType::Cocoapods => &PROJECT_COCOAPODS_DIRS,
+            ProjectType::Gitignored => &PROJECT_GITIGNORED_DIRS,
+        };
+
+        let mut dirs = dirs.to_vec();
+        if matches!(self.project_type, ProjectType::Node) {
+            for dir in node_tooling_dirs(&self.path) {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
         }
+        dirs
     }
 
     pub fn name(&self) -> Cow<'_, str> {
         self.path.to_string_lossy()
     }
 
+    /// Resolves [`Project::artifact_dirs`] to the artifact directories that exist on disk,
+    /// including those of any [workspace members](Project::workspace_members).
+    ///
+    /// Glob patterns are matched against paths relative to the project root, the search doesn't
+    /// descend into directories that are already artifacts.
+    pub fn artifact_paths(&self, options: &ScanOptions) -> Vec<path::PathBuf> {
+        let mut paths = self.own_artifact_paths(options);
+
+        for member in self.workspace_members() {
+            for member_path in member.own_artifact_paths(options) {
+                if !paths.iter().any(|p| member_path.starts_with(p)) {
+                    paths.push(member_path);
+                }
+            }
+        }
+
+        paths
+    }
+
+    fn own_artifact_paths(&self, options: &ScanOptions) -> Vec<path::PathBuf> {
+        let (patterns, literals): (Vec<&str>, Vec<&str>) = self
+            .artifact_dirs()
+            .into_iter()
+            .partition(|p| is_glob_pattern(p));
+
+        let mut paths: Vec<path::PathBuf> = literals
+            .into_iter()
+            .map(|p| self.path.join(p))
+            .filter(|p| p.is_dir())
+            .collect();
+
+        // Virtual environments can have any name, they're recognised by their pyvenv.cfg
+        if matches!(self.project_type, ProjectType::Python) {
+            paths.extend(virtualenv_dirs(&self.path));
+        }
+
+        // Build trees can have any name, they're recognised by the files the build system
+        // generates in them
+        if matches!(
+            self.project_type,
+            ProjectType::CMake | ProjectType::Meson | ProjectType::Autotools
+        ) {
+            for build_tree in build_trees(&self.path) {
+                if !paths.iter().any(|p| build_tree.starts_with(p)) {
+                    paths.push(build_tree);
+                }
+            }
+        }
+
+        if matches!(self.project_type, ProjectType::Bazel) {
+            paths.extend(bazel_output_base(&self.path));
+        }
+
+        if matches!(self.project_type, ProjectType::Latex) {
+            paths.extend(latex_output_files(&self.path));
+        }
+
+        // Without a threshold every ignored directory would count
+        if let (ProjectType::Gitignored, Some(min_size)) =
+            (&self.project_type, options.gitignored_min_size)
+        {
+            paths.extend(gitignore::ignored_dirs(&self.path, min_size, options));
+        }
+
+        // Any kind of project can come with a flake or an .envrc
+        for nix_path in nix_artifact_paths(&self.path) {
+            if !paths.contains(&nix_path) {
+                paths.push(nix_path);
+            }
+        }
+
+        // A target-dir configured inside the project is an artifact like `target`
+        if let Some(target_dir) = self.configured_target_dir(CargoEnv::process()) {
+            if target_dir.path.starts_with(&self.path)
+                && target_dir.path.is_dir()
+                && !paths.contains(&target_dir.path)
+            {
+                paths.push(target_dir.path);
+            }
+        }
+
+        let glob_paths = self.glob_artifact_paths(&patterns, &paths, options);
+        paths.extend(glob_paths);
+
+        // Caches can be tagged by any tool, whatever the type of project. Those inside other
+        // artifacts are removed along with them.
+        for cache_dir in tagged_cache_dirs(&self.path, &paths, options) {
+            if !paths.iter().any(|p| cache_dir.starts_with(p)) {
+                paths.push(cache_dir);
+            }
+        }
+
+        paths
+    }
+
+    /// Directories matching the glob `patterns`, without descending into the `known` artifacts
+    fn glob_artifact_paths(
+        &self,
+        patterns: &[&str],
+        known: &[path::PathBuf],
+        options: &ScanOptions,
+    ) -> Vec<path::PathBuf> {
+        let mut paths = Vec::new();
+        if patterns.is_empty() {
+            return paths;
+        }
+
+        let mut globs = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            match globset::GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+            {
+                Ok(glob) => {
+                    globs.add(glob);
+                }
+                Err(e) => eprintln!("invalid artifact pattern {pattern:?}: {e}"),
+            }
+        }
+        let globs = match globs.build() {
+            Ok(globs) => globs,
+            Err(_) => return paths,
+        };
+
+        let mut it = walkdir::WalkDir::new(&self.path)
+            .min_depth(1)
+            .follow_links(options.follow_symlinks)
+            .same_file_system(options.same_file_system)
+            .into_iter();
+
+        while let Some(entry) = it.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            // Symlinked artifacts are matched too, they're only unlinked when cleaned
+            let linked_dir = entry.path_is_symlink() && entry.path().is_dir();
+            if !entry.file_type().is_dir() && !linked_dir {
+                continue;
+            }
+            if entry.file_name() == ".git" || known.iter().any(|p| p == entry.path()) {
+                it.skip_current_dir();
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(&self.path) else {
+                continue;
+            };
+            if globs.is_match(relative) {
+                paths.push(entry.path().to_path_buf());
+                it.skip_current_dir();
+            }
+        }
+
+        paths
+    }
+
+    fn configured_target_dir(&self, env: &CargoEnv) -> Option<TargetDir> {
+        match self.project_type {
+            ProjectType::Cargo => cargo::configured_target_dir(&self.path, env),
+            _ => None,
+        }
+    }
+
+    /// The Cargo target directory when it's configured to live outside of the project. It isn't
+    /// part of [`Project::artifact_paths`] and isn't removed by [`Project::clean`], as it may
+    /// hold the builds of other projects too, see [`group_target_dirs`].
+    pub fn external_target_dir(&self) -> Option<TargetDir> {
+        self.configured_target_dir(CargoEnv::process())
+            .filter(|t| !t.path.starts_with(&self.path) && t.path.is_dir())
+    }
+
+    /// For Cargo projects, finds the stale content of the target directory that can be removed
+    /// without a full rebuild, see [`sweep`].
+    pub fn cargo_sweep(&self, options: &SweepOptions) -> Option<Sweep> {
+        if !matches!(self.project_type, ProjectType::Cargo) {
+            return None;
+        }
+        let target_dir = self
+            .configured_target_dir(&options.env)
+            .map(|t| t.path)
+            .unwrap_or_else(|| self.path.join("target"));
+        target_dir
+            .is_dir()
+            .then(|| cargo::sweep(&target_dir, options))
+    }
+
+    /// Projects declared as members of a workspace rooted at this project, from `workspaces` in
+    /// package.json, pnpm-workspace.yaml, Cargo's `[workspace] members`, or Gradle settings
+    /// `include`s.
+    pub fn workspace_members(&self) -> Vec<Project> {
+        let patterns = match self.project_type {
+            ProjectType::Node | ProjectType::Turborepo => {
+                let mut patterns = read_package_json_workspaces(&self.path);
+                patterns.extend(read_pnpm_workspace(&self.path));
+                patterns
+            }
+            ProjectType::Cargo => read_cargo_workspace_members(&self.path),
+            ProjectType::Gradle => read_gradle_includes(&self.path),
+            _ => return Vec::new(),
+        };
+
+        expand_workspace_patterns(&self.path, &patterns)
+            .into_iter()
+            .filter(|member| *member != self.path)
+            .map(|member| Project {
+                // Gradle modules don't need their own build file
+                project_type: detect_project_type(&member)
+                    .unwrap_or_else(|| self.project_type.clone()),
+                path: member,
+            })
+            .collect()
+    }
+
+    /// [`Project::artifact_paths`] with their [sizes](artifact_size). Finding the paths means
+    /// walking the project, they can be kept for [`Project::size_dirs_of`] and
+    /// [`Project::clean_artifacts`] rather than found again.
+    pub fn artifact_sizes(&self, options: &ScanOptions) -> Vec<(path::PathBuf, u64)> {
+        self.artifact_paths(options)
+            .into_iter()
+            .map(|p| {
+                let size = artifact_size(&p, options);
+                (p, size)
+            })
+            .collect()
+    }
+
     pub fn size(&self, options: &ScanOptions) -> u64 {
-        self.artifact_dirs()
+        self.artifact_sizes(options)
             .iter()
-            .copied()
-            .map(|p| dir_size(&self.path.join(p), options))
+            .map(|(_, size)| size)
             .sum()
     }
 
@@ -228,6 +866,15 @@ impl Project {
     }
 
     pub fn size_dirs(&self, options: &ScanOptions) -> ProjectSize {
+        self.size_dirs_of(self.artifact_sizes(options), options)
+    }
+
+    /// Like [`Project::size_dirs`], for the artifacts found by [`Project::artifact_sizes`]
+    pub fn size_dirs_of(
+        &self,
+        artifact_dirs: Vec<(path::PathBuf, u64)>,
+        options: &ScanOptions,
+    ) -> ProjectSize {
         let mut artifact_size = 0;
         let mut non_artifact_size = 0;
         let mut dirs = Vec::new();
@@ -238,6 +885,7 @@ impl Project {
                     artifact_size,
                     non_artifact_size,
                     dirs,
+                    artifact_dirs: Vec::new(),
                 }
             }
             Ok(rd) => rd,
@@ -250,8 +898,12 @@ impl Project {
             };
 
             if file_type.is_file() {
+                // Artifact files are counted with the other top level artifacts below
+                let artifact_file = artifact_dirs.iter().any(|(p, _)| *p == entry.path());
                 if let Ok(metadata) = entry.metadata() {
-                    non_artifact_size += metadata.len();
+                    if !artifact_file {
+                        non_artifact_size += metadata.len();
+                    }
                 }
                 continue;
             }
@@ -261,24 +913,58 @@ impl Project {
                     Err(_) => continue,
                     Ok(file_name) => file_name,
                 };
-                let size = dir_size(&entry.path(), options);
-                let artifact_dir = self.artifact_dirs().contains(&file_name.as_str());
+                let path = entry.path();
+                let size = dir_size(&path, options);
+                let artifact_dir = artifact_dirs.iter().any(|(p, _)| *p == path);
                 if artifact_dir {
                     artifact_size += size;
                 } else {
-                    non_artifact_size += size;
+                    // Artifacts nested further down, like `src/pkg/__pycache__`
+                    let nested_size: u64 = artifact_dirs
+                        .iter()
+                        .filter(|(p, _)| p.starts_with(&path))
+                        .map(|(_, size)| size)
+                        .sum();
+                    artifact_size += nested_size;
+                    non_artifact_size += size.saturating_sub(nested_size);
                 }
                 dirs.push((file_name, size, artifact_dir));
             }
         }
 
+        // Artifacts that aren't within a directory of the project, like Bazel's output base or
+        // Nix `result` links
+        artifact_size += artifact_dirs
+            .iter()
+            .filter(|(p, _)| {
+                !dirs
+                    .iter()
+                    .any(|(name, _, _)| p.starts_with(self.path.join(name)))
+            })
+            .map(|(_, size)| size)
+            .sum::<u64>();
+
+        let artifact_dirs = artifact_dirs
+            .into_iter()
+            .map(|(p, size)| (self.relative_display(&p), size))
+            .collect();
+
         ProjectSize {
             artifact_size,
             non_artifact_size,
             dirs,
+            artifact_dirs,
         }
     }
 
+    /// Displays `path` relative to the project root when it's inside the project
+    pub fn relative_display(&self, path: &Path) -> String {
+        path.strip_prefix(&self.path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    }
+
     pub fn type_name(&self) -> &'static str {
         match self.project_type {
             ProjectType::Cargo => PROJECT_CARGO_NAME,
@@ -296,36 +982,280 @@ impl Project {
             ProjectType::Maven => PROJECT_MVN_NAME,
             ProjectType::Unreal => PROJECT_UNREAL_NAME,
             ProjectType::Jupyter => PROJECT_JUPYTER_NAME,
-            ProjectType::Python => PROJECT_PYTHON_NAME,
+            ProjectType::Python => {
+                if is_python_package(&self.path) {
+                    PROJECT_PYTHON_NAME
+                } else {
+                    PROJECT_PYTHON_SCRIPTS_NAME
+                }
+            }
             ProjectType::Pixi => PROJECT_PIXI_NAME,
             ProjectType::CMake => PROJECT_CMAKE_NAME,
+            ProjectType::Meson => PROJECT_MESON_NAME,
+            ProjectType::Autotools => PROJECT_AUTOTOOLS_NAME,
+            ProjectType::Bazel => PROJECT_BAZEL_NAME,
+            ProjectType::Buck2 => PROJECT_BUCK2_NAME,
+            ProjectType::Pants => PROJECT_PANTS_NAME,
+            ProjectType::Nix => PROJECT_NIX_NAME,
+            ProjectType::Ruby => PROJECT_RUBY_NAME,
+            ProjectType::Go => PROJECT_GO_NAME,
+            ProjectType::Dune => PROJECT_DUNE_NAME,
+            ProjectType::Rebar3 => PROJECT_REBAR3_NAME,
+            ProjectType::Julia => PROJECT_JULIA_NAME,
+            ProjectType::Renv => PROJECT_RENV_NAME,
+            ProjectType::Latex => PROJECT_LATEX_NAME,
             ProjectType::Composer => PROJECT_COMPOSER_NAME,
             ProjectType::Pub => PROJECT_PUB_NAME,
             ProjectType::Elixir => PROJECT_ELIXIR_NAME,
             ProjectType::Swift => PROJECT_SWIFT_NAME,
             ProjectType::Gradle => PROJECT_GRADLE_NAME,
             ProjectType::Zig => PROJECT_ZIG_NAME,
+            ProjectType::Godot3 => PROJECT_GODOT_3_NAME,
             ProjectType::Godot4 => PROJECT_GODOT_4_NAME,
             ProjectType::Dotnet => PROJECT_DOTNET_NAME,
             ProjectType::Turborepo => PROJECT_TURBOREPO_NAME,
             ProjectType::Terraform => PROJECT_TERRAFORM_NAME,
             ProjectType::Cocoapods => PROJECT_COCOAPODS_NAME,
+            ProjectType::Gitignored => PROJECT_GITIGNORED_NAME,
         }
     }
 
-    /// Deletes the project's artifact directories and their contents
-    pub fn clean(&self) {
-        for artifact_dir in self
-            .artifact_dirs()
+    /// Whether the contents of `path`, one of the [`Project::artifact_paths`], look like what the
+    /// build tool generates. Directories with generic names such as `build` or `vendor` may hold
+    /// sources instead, they're unverified unless the tool's marker files are found inside, or
+    /// they're tagged with a `CACHEDIR.TAG`.
+    pub fn is_verified_artifact(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return true;
+        };
+        if is_cachedir_tagged(path) {
+            return true;
+        }
+        // Being ignored is no evidence of what's inside
+        if self.project_type == ProjectType::Gitignored {
+            return false;
+        }
+
+        // Members of a workspace may be a different type of project
+        let owner_type = self
+            .workspace_members()
+            .into_iter()
+            .find(|m| path.starts_with(&m.path))
+            .map_or_else(|| self.project_type.clone(), |m| m.project_type);
+
+        let markers = ARTIFACT_MARKERS
             .iter()
-            .copied()
-            .map(|ad| self.path.join(ad))
-            .filter(|ad| ad.exists())
+            .find(|(project_type, dir, _)| *project_type == owner_type && *dir == name);
+        match markers {
+            Some((_, _, markers)) => contains_marker(path, markers),
+            // Build trees found by their content can have generic names too
+            None => {
+                !GENERIC_ARTIFACT_DIRS.contains(&name)
+                    || is_build_tree(path, &dir_file_names(path).unwrap_or_default())
+            }
+        }
+    }
+
+    /// Reasons not to remove `path`, one of the [`Project::artifact_paths`], because it's
+    /// unlikely to be a build artifact. Artifacts outside the project, like Bazel's output base,
+    /// are verified when they're found and aren't checked.
+    pub fn clean_refusal(&self, path: &Path) -> Option<CleanRefusal> {
+        if !path.starts_with(&self.path) || path.is_symlink() || !path.is_dir() {
+            return None;
+        }
+        contents_refusal(path)
+    }
+
+    /// Why `target`, what the symlinked artifact `link` leads to outside of the project, isn't
+    /// removed along with the link. It has to pass the checks an artifact inside the project
+    /// would, and its contents are verified under the link's name.
+    pub fn link_target_refusal(&self, link: &Path, target: &Path) -> Option<LinkTargetRefusal> {
+        let project = fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+        let home = env::home_dir().and_then(|home| fs::canonicalize(home).ok());
+        if target.parent().is_none()
+            || project.starts_with(target)
+            || home.is_some_and(|home| home.starts_with(target))
+        {
+            return Some(LinkTargetRefusal::Protected);
+        }
+        if let Some(refusal) = contents_refusal(target) {
+            return Some(LinkTargetRefusal::Refused(refusal));
+        }
+        if !self.is_verified_artifact(link) {
+            return Some(LinkTargetRefusal::Unverified);
+        }
+        nested_mount_point(target).map(LinkTargetRefusal::MountPoint)
+    }
+
+    /// Whether `path`, one of the [`Project::artifact_paths`], is a symlink or contains symlinks
+    /// or mount points leading outside of the project. Nix `result` links into the store are
+    /// expected and aren't reported, nor are artifacts outside the project like Bazel's output
+    /// base.
+    pub fn artifact_escape(&self, path: &Path) -> Option<ArtifactEscape> {
+        if !path.starts_with(&self.path) {
+            return None;
+        }
+        let project = fs::canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+        let outside = |link: &Path| {
+            let target = fs::canonicalize(link).unwrap_or_else(|_| link.to_path_buf());
+            (!target.starts_with(&project) && !target.starts_with(DIR_NIX_STORE)).then_some(target)
+        };
+
+        if path.is_symlink() {
+            return outside(path).map(ArtifactEscape::Symlink);
+        }
+        if !path.is_dir() {
+            return None;
+        }
+
+        let device = other_filesystem(path);
+
+        // Mount points take precedence, removing the directory would descend into them
+        let mut nested_symlink = None;
+        for entry in walkdir::WalkDir::new(path)
+            .min_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
         {
-            if let Err(e) = fs::remove_dir_all(&artifact_dir) {
+            if entry.path_is_symlink() {
+                if nested_symlink.is_none() {
+                    nested_symlink = outside(entry.path())
+                        .map(|target| ArtifactEscape::NestedSymlink(entry.into_path(), target));
+                }
+            } else if entry.file_type().is_dir() && device(entry.path()) {
+                return Some(ArtifactEscape::MountPoint(entry.into_path()));
+            }
+        }
+        nested_symlink
+    }
+
+    /// Whether a build is running in the project, or a process has its working directory or an
+    /// open file in it. Processes are only found on Linux, and this process and the ones that
+    /// started it don't count.
+    pub fn in_use(&self) -> Option<InUse> {
+        self.in_use_with(
+            &self.artifact_paths(&ScanOptions::default()),
+            &Processes::snapshot(),
+        )
+    }
+
+    /// Like [`Project::in_use`], for artifact paths that have already been found and processes in
+    /// a snapshot taken beforehand
+    pub fn in_use_with(
+        &self,
+        artifact_paths: &[path::PathBuf],
+        processes: &Processes,
+    ) -> Option<InUse> {
+        artifact_paths
+            .iter()
+            .find_map(|artifact_dir| in_use::held_build_lock(artifact_dir))
+            .map(InUse::BuildLock)
+            .or_else(|| processes.using(&self.path))
+    }
+
+    /// Deletes the project's artifact directories and their contents. Projects that are
+    /// [in use](Project::in_use) and directories that have a [`CleanRefusal`] are left alone, and
+    /// symlinks leading outside of the project are only unlinked.
+    pub fn clean(&self) {
+        if let Err(in_use) = self.clean_with(&ScanOptions::default(), &CleanOptions::default()) {
+            eprintln!("not cleaning {:?}, it {in_use}", self.path);
+        }
+    }
+
+    /// Like [`Project::clean`], returning why nothing was removed when the project is in use.
+    /// The artifact directories are found with the `scan_options` the project was found with, so
+    /// that what's removed is what was reported.
+    pub fn clean_with(
+        &self,
+        scan_options: &ScanOptions,
+        options: &CleanOptions,
+    ) -> Result<(), InUse> {
+        self.clean_artifacts(
+            &self.artifact_paths(scan_options),
+            options,
+            &Processes::snapshot(),
+        )
+    }
+
+    /// Like [`Project::clean_with`], removing only the given `artifact_paths`, such as the ones a
+    /// user has been shown and confirmed. When cleaning many projects, one snapshot of the
+    /// `processes` can be shared between them.
+    pub fn clean_artifacts(
+        &self,
+        artifact_paths: &[path::PathBuf],
+        options: &CleanOptions,
+        processes: &Processes,
+    ) -> Result<(), InUse> {
+        if !options.allow_in_use {
+            if let Some(in_use) = self.in_use_with(artifact_paths, processes) {
+                return Err(in_use);
+            }
+        }
+
+        let bazel = matches!(self.project_type, ProjectType::Bazel);
+
+        for artifact_dir in artifact_paths {
+            if options.verified_only && !self.is_verified_artifact(artifact_dir) {
+                continue;
+            }
+            if !options.allow_nested {
+                if let Some(refusal) = self.clean_refusal(artifact_dir) {
+                    eprintln!("not removing {:?}, it {refusal}", artifact_dir);
+                    continue;
+                }
+            }
+
+            match self.artifact_escape(artifact_dir) {
+                Some(ArtifactEscape::Symlink(target)) if options.remove_link_targets => {
+                    match self.link_target_refusal(artifact_dir, &target) {
+                        Some(refusal) => eprintln!("not removing {:?}, it {refusal}", target),
+                        None => match processes.using(&target) {
+                            Some(in_use) => eprintln!("not removing {:?}, it {in_use}", target),
+                            None => remove_path(&target),
+                        },
+                    }
+                    remove_path(artifact_dir);
+                    continue;
+                }
+                Some(ArtifactEscape::Symlink(_)) => {
+                    remove_path(artifact_dir);
+                    continue;
+                }
+                // Removing the directory would descend into the mounted filesystem
+                Some(escape @ ArtifactEscape::MountPoint(_)) if !options.remove_link_targets => {
+                    eprintln!("not removing {:?}, it {escape}", artifact_dir);
+                    continue;
+                }
+                // Nested symlinks are unlinked by `remove_dir_all` without being followed
+                _ => {}
+            }
+
+            // LaTeX intermediates are files
+            if artifact_dir.is_file() && !artifact_dir.is_symlink() {
+                if let Err(e) = fs::remove_file(artifact_dir) {
+                    eprintln!("error removing file {:?}: {:?}", artifact_dir, e);
+                }
+                continue;
+            }
+            // Bazel makes parts of its output base read-only
+            if bazel {
+                make_dirs_writable(artifact_dir);
+            }
+            if let Err(e) = fs::remove_dir_all(artifact_dir) {
                 eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
             }
         }
+
+        // The convenience symlinks would be left dangling
+        if bazel {
+            for link in bazel_symlinks(&self.path) {
+                if let Err(e) = fs::remove_file(&link) {
+                    eprintln!("error removing symlink {:?}: {:?}", link, e);
+                }
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -358,8 +1288,23 @@ fn is_hidden(entry: &walkdir::DirEntry) -> bool {
     entry.file_name().to_string_lossy().starts_with('.')
 }
 
+fn is_skipped_hidden(entry: &walkdir::DirEntry) -> bool {
+    let name = entry.file_name().to_string_lossy();
+    SKIPPED_HIDDEN_DIRS
+        .iter()
+        .any(|skipped| match skipped.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None if skipped.contains('/') => entry.path().ends_with(skipped),
+            None => name == *skipped,
+        })
+}
+
 struct ProjectIter {
     it: walkdir::IntoIter,
+    gitignored: bool,
+    include_hidden: bool,
+    /// Mount points of filesystems the scan doesn't enter
+    skipped_mounts: Vec<path::PathBuf>,
 }
 
 pub enum Red {
@@ -380,109 +1325,824 @@ impl Iterator for ProjectIter {
             if !entry.file_type().is_dir() {
                 continue;
             }
-            if is_hidden(&entry) {
+            // A hidden directory given as the root is still scanned
+            let skip_hidden = entry.depth() > 0
+                && if self.include_hidden {
+                    is_skipped_hidden(&entry)
+                } else {
+                    is_hidden(&entry)
+                };
+            if skip_hidden || self.skipped_mounts.iter().any(|m| m == entry.path()) {
                 self.it.skip_current_dir();
                 continue;
             }
-            let rd = match entry.path().read_dir() {
+            let file_names = match dir_file_names(entry.path()) {
                 Err(e) => return Some(Err(Red::IOError(e))),
-                Ok(rd) => rd,
+                Ok(file_names) => file_names,
             };
-            // intentionally ignoring errors while iterating the ReadDir
-            // can't return them because we'll lose the context of where we are
-            for dir_entry in rd
-                .filter_map(|rd| rd.ok())
-                .filter(|de| de.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-                .map(|de| de.file_name())
-            {
-                let file_name = match dir_entry.to_str() {
-                    None => continue,
-                    Some(file_name) => file_name,
-                };
-                let p_type = match file_name {
-                    FILE_CARGO_TOML => Some(ProjectType::Cargo),
-                    FILE_PACKAGE_JSON => Some(ProjectType::Node),
-                    FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
-                    FILE_STACK_HASKELL => Some(ProjectType::Stack),
-                    FILE_CABAL_HASKELL => Some(ProjectType::Cabal),
-                    FILE_SBT_BUILD => Some(ProjectType::SBT),
-                    FILE_MVN_BUILD => Some(ProjectType::Maven),
-                    FILE_CMAKE_BUILD => Some(ProjectType::CMake),
-                    FILE_COMPOSER_JSON => Some(ProjectType::Composer),
-                    FILE_PUBSPEC_YAML => Some(ProjectType::Pub),
-                    FILE_PIXI_PACKAGE => Some(ProjectType::Pixi),
-                    FILE_ELIXIR_MIX => Some(ProjectType::Elixir),
-                    FILE_SWIFT_PACKAGE => Some(ProjectType::Swift),
-                    FILE_BUILD_GRADLE => Some(ProjectType::Gradle),
-                    FILE_BUILD_GRADLE_KTS => Some(ProjectType::Gradle),
-                    FILE_BUILD_ZIG => Some(ProjectType::Zig),
-                    FILE_GODOT_4_PROJECT => Some(ProjectType::Godot4),
-                    FILE_PROJECT_TURBOREPO => Some(ProjectType::Turborepo),
-                    FILE_TERRAFORM_HCL => Some(ProjectType::Terraform),
-                    FILE_PODFILE => Some(ProjectType::Cocoapods),
-                    file_name if file_name.ends_with(FILE_UNREAL_SUFFIX) => {
-                        Some(ProjectType::Unreal)
-                    }
-                    file_name if file_name.ends_with(FILE_JUPYTER_SUFFIX) => {
-                        Some(ProjectType::Jupyter)
-                    }
-                    file_name if file_name.ends_with(FILE_PYTHON_SUFFIX) => {
-                        Some(ProjectType::Python)
-                    }
-                    file_name
-                        if file_name.ends_with(FILE_CSPROJ_SUFFIX)
-                            || file_name.ends_with(FILE_FSPROJ_SUFFIX) =>
-                    {
-                        if dir_contains_file(entry.path(), FILE_GODOT_4_PROJECT) {
-                            Some(ProjectType::Godot4)
-                        } else if dir_contains_file(entry.path(), FILE_ASSEMBLY_CSHARP) {
-                            Some(ProjectType::Unity)
-                        } else {
-                            Some(ProjectType::Dotnet)
-                        }
-                    }
-                    _ => None,
-                };
-                if let Some(project_type) = p_type {
-                    self.it.skip_current_dir();
-                    return Some(Ok(Project {
-                        project_type,
-                        path: entry.path().to_path_buf(),
-                    }));
-                }
+            // A virtualenv or tagged cache is an artifact of the project it belongs to, not a
+            // project itself
+            if file_names.iter().any(|f| f == FILE_PYVENV_CFG) || is_cachedir_tagged(entry.path()) {
+                self.it.skip_current_dir();
+                continue;
+            }
+            let p_type = project_type_from_files(entry.path(), &file_names);
+            // Same for build trees, unless it's an in-source build
+            if p_type.is_none() && is_build_tree(entry.path(), &file_names) {
+                self.it.skip_current_dir();
+                continue;
+            }
+            if let Some(project_type) = p_type {
+                self.it.skip_current_dir();
+                return Some(Ok(Project {
+                    project_type,
+                    path: entry.path().to_path_buf(),
+                }));
+            }
+            // Projects and repositories within the repository are still looked for, their
+            // artifacts are left to them
+            if self.gitignored && entry.path().join(".git").exists() {
+                return Some(Ok(Project {
+                    project_type: ProjectType::Gitignored,
+                    path: entry.path().to_path_buf(),
+                }));
             }
         }
     }
 }
 
-fn dir_contains_file(path: &Path, file: &str) -> bool {
-    path.read_dir()
-        .map(|rd| {
-            rd.filter_map(|rd| rd.ok()).any(|de| {
-                de.file_type().is_ok_and(|t| t.is_file()) && de.file_name().to_str() == Some(file)
-            })
-        })
-        .unwrap_or(false)
+fn dir_file_names(dir: &Path) -> Result<Vec<String>, std::io::Error> {
+    // intentionally ignoring errors while iterating the ReadDir
+    // can't return them because we'll lose the context of where we are
+    Ok(dir
+        .read_dir()?
+        .filter_map(|rd| rd.ok())
+        .filter(|de| de.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        .filter_map(|de| de.file_name().into_string().ok())
+        .collect())
 }
 
-fn dir_contains_subdir(path: &Path, subdir: &str) -> bool {
-    path.read_dir()
-        .map(|rd| {
-            rd.filter_map(|rd| rd.ok()).any(|de| {
-                de.file_type().is_ok_and(|t| t.is_dir()) && de.file_name().to_str() == Some(subdir)
-            })
-        })
-        .unwrap_or(false)
-}
+/// Identifies the type of project in `dir` from the files it contains. Weak signals such as a
+/// loose `.py` file only count when nothing else matches, and Nix is only the project type when
+/// there's no other build system.
+fn project_type_from_files(dir: &Path, file_names: &[String]) -> Option<ProjectType> {
+    // Unity projects are recognised by their layout, as the .csproj files only exist once the
+    // project has been opened in an IDE
+    if is_unity_project(dir) {
+        return Some(ProjectType::Unity);
+    }
 
-fn is_react_native_project(path: &Path) -> bool {
-    dir_contains_subdir(path, "ios") || dir_contains_subdir(path, "android")
+    file_names
+        .iter()
+        .find_map(|f| project_type_from_file(dir, f))
+        .or_else(|| is_nix_project(file_names).then_some(ProjectType::Nix))
+        .or_else(|| {
+            file_names
+                .iter()
+                .any(|f| f.ends_with(FILE_LATEX_SUFFIX))
+                .then_some(ProjectType::Latex)
+        })
+        .or_else(|| {
+            file_names
+                .iter()
+                .any(|f| f.ends_with(FILE_PYTHON_SUFFIX))
+                .then_some(ProjectType::Python)
+        })
 }
 
-#[derive(Clone, Debug)]
-pub struct ScanOptions {
-    pub follow_symlinks: bool,
+/// Identifies the type of project in `dir` from one of the files it contains
+fn project_type_from_file(dir: &Path, file_name: &str) -> Option<ProjectType> {
+    match file_name {
+        FILE_CARGO_TOML => Some(ProjectType::Cargo),
+        FILE_PACKAGE_JSON => Some(ProjectType::Node),
+        FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
+        FILE_STACK_HASKELL => Some(ProjectType::Stack),
+        FILE_CABAL_HASKELL => Some(ProjectType::Cabal),
+        FILE_SBT_BUILD => Some(ProjectType::SBT),
+        FILE_MVN_BUILD => Some(ProjectType::Maven),
+        FILE_CMAKE_BUILD => Some(ProjectType::CMake),
+        FILE_MESON_BUILD => Some(ProjectType::Meson),
+        FILE_AUTOCONF | FILE_AUTOCONF_LEGACY => Some(ProjectType::Autotools),
+        FILE_BAZEL_MODULE | FILE_BAZEL_WORKSPACE | FILE_BAZEL_WORKSPACE_BAZEL => {
+            Some(ProjectType::Bazel)
+        }
+        FILE_BUCKCONFIG => Some(ProjectType::Buck2),
+        FILE_PANTS_TOML => Some(ProjectType::Pants),
+        FILE_GEMFILE => Some(ProjectType::Ruby),
+        FILE_GO_MOD => Some(ProjectType::Go),
+        FILE_DUNE_PROJECT => Some(ProjectType::Dune),
+        FILE_REBAR_CONFIG => Some(ProjectType::Rebar3),
+        FILE_JULIA_PROJECT => Some(ProjectType::Julia),
+        FILE_RENV_LOCK => Some(ProjectType::Renv),
+        FILE_LATEXMKRC => Some(ProjectType::Latex),
+        FILE_COMPOSER_JSON => Some(ProjectType::Composer),
+        FILE_PUBSPEC_YAML => Some(ProjectType::Pub),
+        FILE_PIXI_PACKAGE => Some(ProjectType::Pixi),
+        FILE_ELIXIR_MIX => Some(ProjectType::Elixir),
+        FILE_SWIFT_PACKAGE => Some(ProjectType::Swift),
+        FILE_BUILD_GRADLE => Some(ProjectType::Gradle),
+        FILE_BUILD_GRADLE_KTS => Some(ProjectType::Gradle),
+        FILE_BUILD_ZIG => Some(ProjectType::Zig),
+        FILE_GODOT_PROJECT => Some(godot_project_type(dir)),
+        FILE_PROJECT_TURBOREPO => Some(ProjectType::Turborepo),
+        FILE_TERRAFORM_HCL => Some(ProjectType::Terraform),
+        FILE_PODFILE => Some(ProjectType::Cocoapods),
+        file_name if file_name.ends_with(FILE_UNREAL_SUFFIX) => Some(ProjectType::Unreal),
+        file_name if file_name.ends_with(FILE_JUPYTER_SUFFIX) => Some(ProjectType::Jupyter),
+        file_name if is_python_marker(file_name) => Some(ProjectType::Python),
+        file_name
+            if file_name.ends_with(FILE_CSPROJ_SUFFIX)
+                || file_name.ends_with(FILE_FSPROJ_SUFFIX) =>
+        {
+            // Unity and Godot projects using C# have these too. Unity projects without their
+            // layout are told apart by the project file Unity generates.
+            if dir_contains_file(dir, FILE_ASSEMBLY_CSHARP) {
+                Some(ProjectType::Unity)
+            } else if dir_contains_file(dir, FILE_GODOT_PROJECT) {
+                Some(godot_project_type(dir))
+            } else {
+                Some(ProjectType::Dotnet)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Identifies the type of project in `dir`, if any
+fn detect_project_type(dir: &Path) -> Option<ProjectType> {
+    project_type_from_files(dir, &dir_file_names(dir).ok()?)
+}
+
+fn is_unity_project(dir: &Path) -> bool {
+    dir.join(FILE_UNITY_PROJECT_VERSION).is_file() && dir.join(DIR_UNITY_ASSETS).is_dir()
+}
+
+/// Godot 3 writes `config_version=4` to project.godot, Godot 4 writes `config_version=5`
+fn godot_project_type(dir: &Path) -> ProjectType {
+    let config_version = fs::read_to_string(dir.join(FILE_GODOT_PROJECT))
+        .ok()
+        .and_then(|project| {
+            project
+                .lines()
+                .find_map(|l| l.trim().strip_prefix("config_version="))
+                .and_then(|v| v.trim().parse::<u32>().ok())
+        });
+
+    match config_version {
+        Some(version) if version <= 4 => ProjectType::Godot3,
+        _ => ProjectType::Godot4,
+    }
+}
+
+fn is_python_marker(file_name: &str) -> bool {
+    matches!(
+        file_name,
+        FILE_PYPROJECT_TOML | FILE_SETUP_PY | FILE_SETUP_CFG | FILE_PIPFILE | FILE_UV_LOCK
+    ) || (file_name.starts_with(FILE_REQUIREMENTS_PREFIX) && file_name.ends_with(".txt"))
+}
+
+/// Python projects with packaging or dependency metadata, as opposed to a folder of scripts
+fn is_python_package(path: &Path) -> bool {
+    dir_file_names(path).is_ok_and(|files| files.iter().any(|f| is_python_marker(f)))
+}
+
+/// CMake, Meson and Autotools build directories, recognised by their generated files. A generated
+/// `build.ninja` alone isn't enough as some projects commit theirs, it has to have been built.
+fn is_build_tree(dir: &Path, file_names: &[String]) -> bool {
+    let has_file = |file: &str| file_names.iter().any(|f| f == file);
+    has_file(FILE_CMAKE_CACHE)
+        || has_file(FILE_AUTOCONF_STATUS)
+        || (has_file(FILE_NINJA_BUILD) && has_file(FILE_NINJA_LOG))
+        || dir.join(DIR_MESON_PRIVATE).is_dir()
+}
+
+/// The source directory a build tree was configured for, as recorded by the build system
+fn build_tree_source(dir: &Path) -> Option<path::PathBuf> {
+    if let Ok(cache) = fs::read_to_string(dir.join(FILE_CMAKE_CACHE)) {
+        return cache
+            .lines()
+            .find_map(|l| l.strip_prefix("CMAKE_HOME_DIRECTORY:INTERNAL="))
+            .map(path::PathBuf::from);
+    }
+
+    if let Ok(info) = fs::read_to_string(dir.join("meson-info/meson-info.json")) {
+        let info: serde_json::Value = serde_json::from_str(&info).ok()?;
+        return info["directories"]["source"]
+            .as_str()
+            .map(path::PathBuf::from);
+    }
+
+    // config.status records the source directory relative to where configure was run
+    let status = fs::read_to_string(dir.join(FILE_AUTOCONF_STATUS)).ok()?;
+    let value = |key: &str| {
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix(key))
+            .map(|v| v.trim_matches('\''))
+    };
+    Some(Path::new(value("ac_pwd=")?).join(value("srcdir=")?))
+}
+
+/// Build trees anywhere under the project root that were configured for sources in this project,
+/// or whose sources can't be determined
+fn build_trees(root: &Path) -> Vec<path::PathBuf> {
+    let root_canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let belongs_to_root = |source: &Path| {
+        source
+            .canonicalize()
+            .unwrap_or_else(|_| source.to_path_buf())
+            .starts_with(&root_canonical)
+    };
+
+    let mut trees = Vec::new();
+    let mut it = walkdir::WalkDir::new(root).min_depth(1).into_iter();
+    while let Some(entry) = it.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if entry.file_name() == ".git" {
+            it.skip_current_dir();
+            continue;
+        }
+        let Ok(file_names) = dir_file_names(entry.path()) else {
+            continue;
+        };
+        if is_build_tree(entry.path(), &file_names) {
+            it.skip_current_dir();
+            if build_tree_source(entry.path()).is_none_or(|s| belongs_to_root(&s)) {
+                trees.push(entry.path().to_path_buf());
+            }
+        }
+    }
+    trees
+}
+
+fn contains_marker(dir: &Path, markers: &[&str]) -> bool {
+    const MARKER_DEPTH: usize = 3;
+
+    let (patterns, names): (Vec<&str>, Vec<&str>) = markers.iter().partition(|m| m.contains('*'));
+    if names.iter().any(|name| dir.join(name).exists()) {
+        return true;
+    }
+
+    let Ok(patterns) = patterns
+        .iter()
+        .try_fold(globset::GlobSetBuilder::new(), |mut set, p| {
+            set.add(globset::Glob::new(p)?);
+            Ok::<_, globset::Error>(set)
+        })
+        .and_then(|set| set.build())
+    else {
+        return false;
+    };
+
+    !patterns.is_empty()
+        && walkdir::WalkDir::new(dir)
+            .min_depth(1)
+            .max_depth(MARKER_DEPTH)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .any(|e| patterns.is_match(e.file_name()))
+}
+
+/// The `bazel-bin`, `bazel-out`, `bazel-<workspace>` etc. symlinks Bazel creates in the workspace
+fn bazel_symlinks(workspace: &Path) -> Vec<path::PathBuf> {
+    let Ok(rd) = workspace.read_dir() else {
+        return Vec::new();
+    };
+    rd.filter_map(|rd| rd.ok())
+        .filter(|de| de.file_type().is_ok_and(|t| t.is_symlink()))
+        .filter(|de| de.file_name().to_string_lossy().starts_with("bazel-"))
+        .map(|de| de.path())
+        .collect()
+}
+
+/// The output base of a Bazel workspace, usually `~/.cache/bazel/_bazel_<user>/<hash>`, found by
+/// following the convenience symlinks into its `execroot`. Bazel records the workspace an output
+/// base belongs to in `DO_NOT_BUILD_HERE`, output bases of other workspaces are never returned.
+fn bazel_output_base(workspace: &Path) -> Option<path::PathBuf> {
+    let workspace = workspace.canonicalize().ok()?;
+
+    bazel_symlinks(&workspace).into_iter().find_map(|link| {
+        let target = link.canonicalize().ok()?;
+        let output_base = target
+            .ancestors()
+            .find(|a| a.file_name().is_some_and(|n| n == "execroot"))?
+            .parent()?;
+        if output_base.starts_with(&workspace) {
+            return None;
+        }
+
+        // Without the record there's no telling whose output base it is
+        let owner = fs::read_to_string(output_base.join("DO_NOT_BUILD_HERE")).ok()?;
+        let owner = Path::new(owner.trim()).canonicalize().ok()?;
+        (owner == workspace).then(|| output_base.to_path_buf())
+    })
+}
+
+/// [`CleanRefusal`]s found by looking inside the directory at `path`
+fn contents_refusal(path: &Path) -> Option<CleanRefusal> {
+    // Deep enough for checkouts of vendored packages, e.g. `vendor/org/package/.git`
+    const REPOSITORY_DEPTH: usize = 3;
+
+    let file_names = dir_file_names(path).unwrap_or_default();
+    let nested_type = file_names
+        .iter()
+        .find_map(|f| project_type_from_file(path, f))
+        // Notebook checkpoints are copies of the notebooks
+        .filter(|t| *t != ProjectType::Jupyter);
+    if let Some(project_type) = nested_type {
+        let nested = Project {
+            project_type,
+            path: path.to_path_buf(),
+        };
+        return Some(CleanRefusal::NestedProject(nested.type_name()));
+    }
+
+    walkdir::WalkDir::new(path)
+        .max_depth(REPOSITORY_DEPTH)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .find(|e| {
+            e.file_type().is_dir()
+                && matches!(e.file_name().to_str(), Some(".git" | ".hg" | ".svn"))
+        })
+        .map(|e| CleanRefusal::NestedRepository(e.path().to_path_buf()))
+}
+
+/// Whether a path is on another filesystem than `root`. Always false outside of Unix.
+fn other_filesystem(root: &Path) -> impl Fn(&Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let device = |path: &Path| fs::metadata(path).ok().map(|m| m.dev());
+        let root = device(root);
+        move |path: &Path| device(path) != root
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = root;
+        |_: &Path| false
+    }
+}
+
+/// The first directory inside `path` that another filesystem is mounted at
+fn nested_mount_point(path: &Path) -> Option<path::PathBuf> {
+    let device = other_filesystem(path);
+    walkdir::WalkDir::new(path)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_type().is_dir() && device(e.path()))
+        .map(|e| e.into_path())
+}
+
+/// Removes a file, symlink or directory tree without following symlinks
+fn remove_path(path: &Path) {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return;
+    };
+    let result = if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else if metadata.is_symlink() && cfg!(windows) && path.is_dir() {
+        // Directory symlinks are directories on windows
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    };
+    if let Err(e) = result {
+        eprintln!("error removing {:?}: {:?}", path, e);
+    }
+}
+
+fn make_dirs_writable(path: &Path) {
+    for entry in walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        // Only directory permissions prevent removing their contents on unix, files matter too
+        // on windows
+        if cfg!(unix) && !entry.file_type().is_dir() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let mut permissions = metadata.permissions();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            permissions.set_mode(permissions.mode() | 0o700);
+        }
+        #[cfg(not(unix))]
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        let _ = fs::set_permissions(entry.path(), permissions);
+    }
+}
+
+/// Nix expressions mark a Nix project, an `.envrc` doesn't as direnv is used with all kinds of
+/// tools
+fn is_nix_project(file_names: &[String]) -> bool {
+    file_names.iter().any(|f| {
+        matches!(
+            f.as_str(),
+            FILE_NIX_FLAKE | FILE_NIX_SHELL | FILE_NIX_DEFAULT
+        )
+    })
+}
+
+/// Whether the `.envrc` in `dir` loads its environment from Nix through `use flake` or `use nix`,
+/// e.g. from a flake kept elsewhere
+fn envrc_uses_nix(dir: &Path) -> bool {
+    fs::read_to_string(dir.join(FILE_ENVRC)).is_ok_and(|envrc| {
+        envrc.lines().any(|line| {
+            let mut words = line.split_whitespace();
+            words.next() == Some("use") && matches!(words.next(), Some("flake" | "nix"))
+        })
+    })
+}
+
+/// The `result` and `result-*` symlinks left by `nix build`, which keep their store paths alive
+/// as garbage collector roots, and direnv's `.direnv` cache
+fn nix_artifact_paths(path: &Path) -> Vec<path::PathBuf> {
+    let Ok(file_names) = dir_file_names(path) else {
+        return Vec::new();
+    };
+    if !is_nix_project(&file_names) && !envrc_uses_nix(path) {
+        return Vec::new();
+    }
+
+    let mut paths: Vec<path::PathBuf> = path
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|rd| rd.ok())
+        .filter(|de| de.file_type().is_ok_and(|t| t.is_symlink()))
+        .map(|de| de.path())
+        .filter(|p| is_nix_result_link(p) && p.exists())
+        .collect();
+
+    let direnv = path.join(".direnv");
+    if direnv.is_dir() {
+        paths.push(direnv);
+    }
+
+    paths.sort();
+    paths
+}
+
+fn is_nix_result_link(path: &Path) -> bool {
+    path.file_name()
+        .map(|name| name.to_string_lossy())
+        .is_some_and(|name| name == "result" || name.starts_with("result-"))
+}
+
+/// The intermediate files LaTeX and latexmk leave next to each document, e.g. `thesis.aux` for
+/// `thesis.tex`. Only files named after a document are considered.
+fn latex_output_files(path: &Path) -> Vec<path::PathBuf> {
+    let Ok(file_names) = dir_file_names(path) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<path::PathBuf> = file_names
+        .iter()
+        .filter_map(|f| f.strip_suffix(FILE_LATEX_SUFFIX))
+        .flat_map(|job| {
+            LATEX_OUTPUT_EXTENSIONS
+                .iter()
+                .map(move |ext| format!("{job}.{ext}"))
+        })
+        .filter(|output| file_names.contains(output))
+        .map(|output| path.join(output))
+        .collect();
+    files.sort();
+    files
+}
+
+fn virtualenv_dirs(path: &Path) -> Vec<path::PathBuf> {
+    let Ok(rd) = path.read_dir() else {
+        return Vec::new();
+    };
+    rd.filter_map(|rd| rd.ok())
+        .filter(|de| de.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|de| de.path())
+        .filter(|p| p.join(FILE_PYVENV_CFG).is_file())
+        .collect()
+}
+
+/// Whether `dir` holds a `CACHEDIR.TAG` starting with the signature from the Cache Directory
+/// Tagging Specification, marking the directory as a cache that can be regenerated.
+pub fn is_cachedir_tagged(dir: &Path) -> bool {
+    let Ok(mut file) = fs::File::open(dir.join(FILE_CACHEDIR_TAG)) else {
+        return false;
+    };
+    let mut signature = [0; CACHEDIR_TAG_SIGNATURE.len()];
+    file.read_exact(&mut signature).is_ok() && signature == CACHEDIR_TAG_SIGNATURE
+}
+
+/// Tagged caches anywhere in the project at `path`. Other projects within it, repositories and
+/// the `known` artifact directories aren't looked inside.
+fn tagged_cache_dirs(
+    path: &Path,
+    known: &[path::PathBuf],
+    options: &ScanOptions,
+) -> Vec<path::PathBuf> {
+    let mut it = walkdir::WalkDir::new(path)
+        .min_depth(1)
+        .follow_links(options.follow_symlinks)
+        .same_file_system(options.same_file_system)
+        .into_iter();
+
+    let mut dirs = Vec::new();
+    while let Some(entry) = it.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if entry.file_name() == ".git" || known.iter().any(|p| entry.path().starts_with(p)) {
+            it.skip_current_dir();
+            continue;
+        }
+        if is_cachedir_tagged(entry.path()) {
+            it.skip_current_dir();
+            dirs.push(entry.into_path());
+            continue;
+        }
+        if detect_project_type(entry.path()).is_some() {
+            it.skip_current_dir();
+        }
+    }
+    dirs.sort();
+    dirs
+}
+
+fn dir_contains_file(path: &Path, file: &str) -> bool {
+    path.read_dir()
+        .map(|rd| {
+            rd.filter_map(|rd| rd.ok()).any(|de| {
+                de.file_type().is_ok_and(|t| t.is_file()) && de.file_name().to_str() == Some(file)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn dir_contains_subdir(path: &Path, subdir: &str) -> bool {
+    path.read_dir()
+        .map(|rd| {
+            rd.filter_map(|rd| rd.ok()).any(|de| {
+                de.file_type().is_ok_and(|t| t.is_dir()) && de.file_name().to_str() == Some(subdir)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn read_package_json_workspaces(dir: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(dir.join(FILE_PACKAGE_JSON)) else {
+        return Vec::new();
+    };
+    let Ok(package) = serde_json::from_str::<serde_json::Value>(&contents) else {
+        return Vec::new();
+    };
+
+    // Either `"workspaces": [...]` or yarn's `"workspaces": { "packages": [...] }`
+    let workspaces = match &package["workspaces"] {
+        serde_json::Value::Object(o) => o.get("packages"),
+        workspaces => Some(workspaces),
+    };
+
+    workspaces
+        .and_then(|w| w.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn read_pnpm_workspace(dir: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(dir.join(FILE_PNPM_WORKSPACE)) else {
+        return Vec::new();
+    };
+
+    // Only the `packages:` list is needed, so avoid pulling in a YAML parser
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            let item = item.split(" #").next().unwrap_or_default().trim();
+            patterns.push(item.trim_matches(['"', '\'']).to_owned());
+        }
+    }
+    patterns
+}
+
+fn read_cargo_workspace_members(dir: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(dir.join(FILE_CARGO_TOML)) else {
+        return Vec::new();
+    };
+    let Ok(manifest) = contents.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return Vec::new();
+    };
+
+    let list = |key| {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+    };
+
+    list("members")
+        .map(str::to_owned)
+        .chain(list("exclude").map(|e| format!("!{e}")))
+        .collect()
+}
+
+fn read_gradle_includes(dir: &Path) -> Vec<String> {
+    let Some(contents) = [FILE_SETTINGS_GRADLE, FILE_SETTINGS_GRADLE_KTS]
+        .iter()
+        .find_map(|f| fs::read_to_string(dir.join(f)).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut modules = Vec::new();
+    let mut lines = contents.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        let Some(rest) = line.strip_prefix("include") else {
+            continue;
+        };
+        if !rest.starts_with([' ', '(', '\'', '"']) {
+            // e.g. includeBuild
+            continue;
+        }
+
+        // `include(` in Kotlin script may span several lines
+        let mut statement = rest.to_owned();
+        if statement.contains('(') {
+            while !statement.contains(')') {
+                match lines.next() {
+                    Some(next) => statement.push_str(next),
+                    None => break,
+                }
+            }
+        }
+
+        // Module paths are the quoted strings, `:libs:core` lives in `libs/core`
+        for (i, part) in statement.split(['\'', '"']).enumerate() {
+            if i % 2 == 1 && !part.is_empty() {
+                modules.push(part.trim_start_matches(':').replace(':', "/"));
+            }
+        }
+    }
+    modules
+}
+
+/// Finds the directories matching workspace member patterns such as `packages/*`, patterns
+/// starting with `!` exclude directories.
+fn expand_workspace_patterns(root: &Path, patterns: &[String]) -> Vec<path::PathBuf> {
+    // Deep enough for `**` patterns in real workspaces without walking everything
+    const MAX_WORKSPACE_DEPTH: usize = 6;
+
+    let mut includes = globset::GlobSetBuilder::new();
+    let mut excludes = globset::GlobSetBuilder::new();
+    let mut max_depth = 0;
+
+    for pattern in patterns {
+        let (set, pattern) = match pattern.strip_prefix('!') {
+            Some(p) => (&mut excludes, p),
+            None => (&mut includes, pattern.as_str()),
+        };
+        let pattern = pattern.trim_start_matches("./").trim_end_matches('/');
+        if pattern.is_empty() {
+            continue;
+        }
+        if let Ok(glob) = globset::GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+        {
+            set.add(glob);
+        }
+        max_depth = max_depth.max(if pattern.contains("**") {
+            MAX_WORKSPACE_DEPTH
+        } else {
+            pattern.split('/').count()
+        });
+    }
+
+    let (Ok(includes), Ok(excludes)) = (includes.build(), excludes.build()) else {
+        return Vec::new();
+    };
+
+    let mut members = Vec::new();
+    let mut it = walkdir::WalkDir::new(root)
+        .min_depth(1)
+        .max_depth(max_depth)
+        .into_iter();
+
+    while let Some(entry) = it.next() {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if entry.file_name() == "node_modules" || is_hidden(&entry) {
+            it.skip_current_dir();
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        if includes.is_match(relative) && !excludes.is_match(relative) {
+            members.push(entry.path().to_path_buf());
+        }
+    }
+
+    members
+}
+
+/// Framework and package manager directories that apply to the Node project in `path`, based on
+/// its package.json dependencies and lockfiles
+fn node_tooling_dirs(path: &Path) -> Vec<&'static str> {
+    let package = fs::read_to_string(path.join(FILE_PACKAGE_JSON))
+        .ok()
+        .and_then(|c| serde_json::from_str::<serde_json::Value>(&c).ok())
+        .unwrap_or_default();
+    let dependencies: Vec<&str> = ["dependencies", "devDependencies"]
+        .iter()
+        .filter_map(|key| package[key].as_object())
+        .flat_map(|deps| deps.keys().map(String::as_str))
+        .collect();
+
+    let mut dirs = Vec::new();
+    for (dependency, dependency_dirs) in PROJECT_NODE_DEPENDENCY_DIRS {
+        // Entries ending in `/` match any package in that scope
+        let used = dependencies.iter().any(|d| {
+            if dependency.ends_with('/') {
+                d.starts_with(dependency)
+            } else {
+                *d == dependency
+            }
+        });
+        if used {
+            dirs.extend_from_slice(dependency_dirs);
+        }
+    }
+
+    let files = dir_file_names(path).unwrap_or_default();
+    let has_file = |file: &str| files.iter().any(|f| f == file);
+    if has_file(FILE_YARN_LOCK) && has_file(FILE_YARNRC_YML) {
+        dirs.extend_from_slice(&PROJECT_YARN_BERRY_DIRS);
+    }
+    if has_file(FILE_PNPM_LOCK) {
+        dirs.extend_from_slice(&PROJECT_PNPM_DIRS);
+    }
+
+    dirs
+}
+
+fn is_react_native_project(path: &Path) -> bool {
+    dir_contains_subdir(path, "ios") || dir_contains_subdir(path, "android")
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ScanOptions {
+    pub follow_symlinks: bool,
     pub same_file_system: bool,
+    /// Opts into treating git repositories that aren't any known type of project as
+    /// [`ProjectType::Gitignored`] projects. Their untracked directories that are gitignored and
+    /// at least this many bytes are reported as unverified artifacts.
+    pub gitignored_min_size: Option<u64>,
+    /// Filesystem types entered below a scan root even though they're in
+    /// [`DEFAULT_SKIPPED_FILESYSTEMS`]
+    pub allowed_filesystems: Vec<String>,
+    /// Filesystem types not entered below a scan root, along with
+    /// [`DEFAULT_SKIPPED_FILESYSTEMS`]
+    pub denied_filesystems: Vec<String>,
+    /// Also look for projects in hidden directories, like `~/.config` or `.worktrees`. Version
+    /// control, cache and trash directories are still skipped, as are package registries and
+    /// toolchains like `~/.cargo` or `~/.m2`.
+    pub include_hidden: bool,
 }
 
 fn build_walkdir_iter<P: AsRef<path::Path>>(path: &P, options: &ScanOptions) -> ProjectIter {
@@ -491,14 +2151,127 @@ fn build_walkdir_iter<P: AsRef<path::Path>>(path: &P, options: &ScanOptions) ->
             .follow_links(options.follow_symlinks)
             .same_file_system(options.same_file_system)
             .into_iter(),
+        gitignored: options.gitignored_min_size.is_some(),
+        include_hidden: options.include_hidden,
+        skipped_mounts: Vec::new(),
     }
 }
 
+/// Finds the projects under `path`. Filesystems mounted below it are skipped when they aren't
+/// [scanned](Mount::is_scanned).
 pub fn scan<P: AsRef<path::Path>>(
     path: &P,
     options: &ScanOptions,
 ) -> impl Iterator<Item = Result<Project, Red>> {
-    build_walkdir_iter(path, options)
+    let mut it = build_walkdir_iter(path, options);
+    it.skipped_mounts = mounts::skipped_mount_points(&mounts(), path.as_ref(), options);
+    it
+}
+
+/// Canonicalises `roots` and drops those that are the same directory as, or are inside, another
+/// root, so nothing is scanned twice. The remaining roots keep their order.
+pub fn normalise_roots<P: AsRef<path::Path>>(roots: &[P]) -> Vec<path::PathBuf> {
+    let roots: Vec<(path::PathBuf, Option<(u64, u64)>)> = roots
+        .iter()
+        .map(|root| {
+            let root = root.as_ref();
+            let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+            let id = file_id(&root);
+            (root, id)
+        })
+        .collect();
+
+    let mut normalised: Vec<(path::PathBuf, Option<(u64, u64)>)> = Vec::new();
+    for (root, id) in &roots {
+        let nested = roots
+            .iter()
+            .any(|(other, _)| other != root && root.starts_with(other));
+        // Bind mounts of the same directory only share their device and inode
+        let duplicate = normalised
+            .iter()
+            .any(|(other, other_id)| other == root || (id.is_some() && other_id == id));
+        if !nested && !duplicate {
+            normalised.push((root.clone(), *id));
+        }
+    }
+    normalised.into_iter().map(|(root, _)| root).collect()
+}
+
+/// Scans each of the [normalised](normalise_roots) `roots`. When following symlinks a project can
+/// be reached through more than one path, it's only reported the first time.
+pub fn scan_roots<'a, P: AsRef<path::Path>>(
+    roots: &[P],
+    options: &'a ScanOptions,
+) -> impl Iterator<Item = Result<Project, Red>> + 'a {
+    let mut seen = HashSet::new();
+    normalise_roots(roots)
+        .into_iter()
+        .flat_map(move |root| scan(&root, options))
+        .filter(move |project| match project {
+            Ok(project) if options.follow_symlinks => {
+                file_id(&project.path).is_none_or(|id| seen.insert(id))
+            }
+            _ => true,
+        })
+}
+
+/// The device and inode of `path`, identifying it whichever way it's reached
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Directories tagged with a `CACHEDIR.TAG` under `path` that aren't inside a project, such as
+/// the caches tools keep in `~/.cache`. Tagged directories within projects are part of their
+/// [`Project::artifact_paths`] instead.
+pub fn scan_tagged_caches<P: AsRef<path::Path>>(
+    path: &P,
+    options: &ScanOptions,
+) -> impl Iterator<Item = path::PathBuf> {
+    let skipped_mounts = mounts::skipped_mount_points(&mounts(), path.as_ref(), options);
+    let mut it = build_walkdir_iter(path, options).it;
+    std::iter::from_fn(move || loop {
+        let entry = match it.next()? {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if skipped_mounts.iter().any(|m| m == entry.path()) {
+            it.skip_current_dir();
+            continue;
+        }
+        if is_cachedir_tagged(entry.path()) {
+            it.skip_current_dir();
+            return Some(entry.into_path());
+        }
+        if entry.file_name() == ".git" || detect_project_type(entry.path()).is_some() {
+            it.skip_current_dir();
+        }
+    })
+}
+
+/// Size of an entry of [`Project::artifact_paths`]. For Nix `result` links that's the size of the
+/// store paths only they keep alive, falling back to the size of the linked store path without
+/// Nix. Other symlinks are only unlinked when cleaned, so only the link itself counts.
+pub fn artifact_size<P: AsRef<path::Path>>(path: &P, options: &ScanOptions) -> u64 {
+    let path = path.as_ref();
+    if let Some(size) = nix::pinned_size(path) {
+        return size;
+    }
+    match path.symlink_metadata() {
+        Ok(metadata) if metadata.is_symlink() && !is_nix_result_link(path) => metadata.len(),
+        _ => dir_size(&path, options),
+    }
 }
 
 // TODO does this need to exist as is??
@@ -534,55 +2307,22 @@ pub fn pretty_size(size: u64) -> String {
 }
 
 pub fn clean(project_path: &str) -> Result<(), Box<dyn error::Error>> {
-    let project = fs::read_dir(project_path)?
-        .filter_map(|rd| rd.ok())
-        .find_map(|dir_entry| {
-            let file_name = dir_entry.file_name().into_string().ok()?;
-            let p_type = match file_name.as_str() {
-                FILE_CARGO_TOML => Some(ProjectType::Cargo),
-                FILE_PACKAGE_JSON => Some(ProjectType::Node),
-                FILE_ASSEMBLY_CSHARP => Some(ProjectType::Unity),
-                FILE_STACK_HASKELL => Some(ProjectType::Stack),
-                FILE_CABAL_HASKELL => Some(ProjectType::Cabal),
-                FILE_SBT_BUILD => Some(ProjectType::SBT),
-                FILE_MVN_BUILD => Some(ProjectType::Maven),
-                FILE_CMAKE_BUILD => Some(ProjectType::CMake),
-                FILE_COMPOSER_JSON => Some(ProjectType::Composer),
-                FILE_PUBSPEC_YAML => Some(ProjectType::Pub),
-                FILE_PIXI_PACKAGE => Some(ProjectType::Pixi),
-                FILE_ELIXIR_MIX => Some(ProjectType::Elixir),
-                FILE_SWIFT_PACKAGE => Some(ProjectType::Swift),
-                FILE_BUILD_ZIG => Some(ProjectType::Zig),
-                FILE_GODOT_4_PROJECT => Some(ProjectType::Godot4),
-                FILE_TERRAFORM_HCL => Some(ProjectType::Terraform),
-                FILE_PODFILE => Some(ProjectType::Cocoapods),
-                _ => None,
-            };
-            if let Some(project_type) = p_type {
-                return Some(Project {
-                    project_type,
-                    path: project_path.into(),
-                });
-            }
-            None
-        });
+    let project_type = project_type_from_files(
+        Path::new(project_path),
+        &dir_file_names(Path::new(project_path))?,
+    );
 
-    if let Some(project) = project {
-        for artifact_dir in project
-            .artifact_dirs()
-            .iter()
-            .copied()
-            .map(|ad| path::PathBuf::from(project_path).join(ad))
-            .filter(|ad| ad.exists())
-        {
-            if let Err(e) = fs::remove_dir_all(&artifact_dir) {
-                eprintln!("error removing directory {:?}: {:?}", artifact_dir, e);
-            }
-        }
+    if let Some(project_type) = project_type {
+        let project = Project {
+            project_type,
+            path: project_path.into(),
+        };
+        project.clean();
     }
 
     Ok(())
 }
+
 pub fn path_canonicalise(
     base: &path::Path,
     tail: path::PathBuf,
@@ -596,7 +2336,1470 @@ pub fn path_canonicalise(
 
 #[cfg(test)]
 mod tests {
-    use super::print_elapsed;
+    use super::{print_elapsed, CleanOptions, CleanRefusal, Project, ProjectType, ScanOptions};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    /// Scratch directory for building project fixtures, removed on drop
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("kondo-test-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn dir(&self, rel: &str) -> &Self {
+            fs::create_dir_all(self.0.join(rel)).unwrap();
+            self
+        }
+
+        fn file(&self, rel: &str, contents: &str) -> &Self {
+            let path = self.0.join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+            self
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn relative_artifact_paths(project: &Project) -> Vec<String> {
+        let mut paths: Vec<String> = project
+            .artifact_paths(&ScanOptions::default())
+            .iter()
+            .map(|p| project.relative_display(p).replace('\\', "/"))
+            .collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn workspace_members() {
+        let td = TestDir::new("workspace-members");
+        td.file(
+            "package.json",
+            r#"{ "workspaces": ["packages/*", "apps/web"] }"#,
+        )
+        .file("packages/ui/package.json", "{}")
+        .dir("packages/ui/node_modules")
+        .file("packages/api/Cargo.toml", "")
+        .dir("packages/api/target")
+        .file("apps/web/package.json", "{}")
+        .dir("apps/web/node_modules")
+        .file("apps/docs/package.json", "{}")
+        .dir("apps/docs/node_modules")
+        .dir("node_modules");
+
+        let project = Project {
+            project_type: ProjectType::Node,
+            path: td.0.clone(),
+        };
+
+        assert_eq!(
+            relative_artifact_paths(&project),
+            [
+                "apps/docs/node_modules",
+                "apps/web/node_modules",
+                "node_modules",
+                "packages/api/target",
+                "packages/ui/node_modules",
+            ]
+        );
+
+        let mut members: Vec<String> = project
+            .workspace_members()
+            .iter()
+            .map(|m| format!("{} {}", project.relative_display(&m.path), m.type_name()))
+            .collect();
+        members.sort();
+        assert_eq!(
+            members,
+            ["apps/web Node", "packages/api Cargo", "packages/ui Node"]
+        );
+    }
+
+    #[test]
+    fn workspace_declarations() {
+        let td = TestDir::new("workspace-declarations");
+        td.file(
+            "pnpm-workspace.yaml",
+            "packages:\n  - 'packages/*'\n  - \"!packages/skip\" # comment\ncatalog:\n  - x\n",
+        )
+        .file(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]\n",
+        )
+        .file(
+            "settings.gradle.kts",
+            "rootProject.name = \"x\"\ninclude(\n  \":app\",\n  \":libs:core\"\n)\ninclude ':cli'\nincludeBuild(\"tools\")\n",
+        );
+
+        assert_eq!(
+            super::read_pnpm_workspace(&td.0),
+            ["packages/*", "!packages/skip"]
+        );
+        assert_eq!(
+            super::read_cargo_workspace_members(&td.0),
+            ["crates/*", "!crates/old"]
+        );
+        assert_eq!(
+            super::read_gradle_includes(&td.0),
+            ["app", "libs/core", "cli"]
+        );
+    }
+
+    #[test]
+    fn cargo_target_dir() {
+        use super::cargo::resolve_target_dir;
+
+        let td = TestDir::new("cargo-target-dir");
+        td.file(
+            "code/.cargo/config.toml",
+            "[build]\ntarget-dir = \"shared-target\"\n",
+        )
+        .file("code/app/Cargo.toml", "")
+        .file("code/lib/Cargo.toml", "")
+        .file("code/lib/.cargo/config", "build.target-dir = \"out\"\n")
+        .file(
+            "code/tool/.cargo/config.toml",
+            "[build]\ntarget-dir = \"../tool-target\"\n",
+        )
+        .file(
+            "cargo-home/config.toml",
+            "[build]\ntarget-dir = \"/ignored\"\n",
+        );
+        let cargo_home = Some(td.0.join("cargo-home"));
+
+        let app = td.0.join("code/app");
+        let app_target = resolve_target_dir(&app, None, cargo_home.clone()).unwrap();
+        assert_eq!(app_target.path, td.0.join("code/shared-target"));
+
+        let lib = td.0.join("code/lib");
+        let lib_target = resolve_target_dir(&lib, None, cargo_home.clone()).unwrap();
+        assert_eq!(lib_target.path, lib.join("out"));
+
+        let tool = td.0.join("code/tool");
+        let target = resolve_target_dir(&tool, None, cargo_home.clone()).unwrap();
+        assert_eq!(target.path, td.0.join("code/tool-target"));
+
+        let target = resolve_target_dir(&lib, Some("/tmp/t".into()), cargo_home).unwrap();
+        assert_eq!(target.path, PathBuf::from("/tmp/t"));
+
+        let target = resolve_target_dir(&td.0, None, Some(td.0.join("none")));
+        assert_eq!(target, None);
+
+        // Only shared once a second project builds into it
+        let project = |path: &Path| Project {
+            project_type: ProjectType::Cargo,
+            path: path.to_path_buf(),
+        };
+        let groups = super::group_target_dirs([
+            (project(&app), app_target.clone()),
+            (project(&lib), lib_target.clone()),
+            (project(&td.0.join("code/other")), app_target),
+        ]);
+        let groups: Vec<(bool, usize)> = groups
+            .iter()
+            .map(|(t, projects)| (t.shared, projects.len()))
+            .collect();
+        assert_eq!(groups, [(true, 2), (false, 1)]);
+    }
+
+    #[test]
+    fn cargo_sweep() {
+        use super::{CargoEnv, SweepOptions, SweepReason};
+        use std::time::{Duration, SystemTime};
+
+        let td = TestDir::new("cargo-sweep");
+        td.file("Cargo.toml", "")
+            .file("target/debug/.fingerprint/app-1/bin-app.json", "{}")
+            .file("target/debug/deps/app-1", "binary")
+            .file(
+                "target/debug/incremental/app-2/s-1/query-cache.bin",
+                "cache",
+            )
+            .file(
+                "target/x86_64-pc-windows-gnu/release/.fingerprint/app-3/bin-app.json",
+                "{}",
+            )
+            .file(
+                "target/x86_64-pc-windows-gnu/release/deps/app-3.exe",
+                "binary",
+            )
+            .file("target/release/deps/app-4", "binary")
+            .dir("target/release/.fingerprint");
+
+        let old_fingerprint =
+            td.0.join("target/x86_64-pc-windows-gnu/release/.fingerprint/app-3/bin-app.json");
+        fs::File::options()
+            .write(true)
+            .open(old_fingerprint)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(60 * 60 * 24 * 60))
+            .unwrap();
+
+        let project = Project {
+            project_type: ProjectType::Cargo,
+            path: td.0.clone(),
+        };
+        // The environment running the tests may point at a real target directory
+        let options = SweepOptions {
+            uninstalled_toolchains: false,
+            env: CargoEnv::default(),
+            ..SweepOptions::default()
+        };
+        let sweep = project.cargo_sweep(&options).unwrap();
+
+        let mut items: Vec<(String, SweepReason)> = sweep
+            .items
+            .iter()
+            .map(|i| {
+                (
+                    project.relative_display(&i.path).replace('\\', "/"),
+                    i.reason,
+                )
+            })
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            items,
+            [
+                (
+                    "target/debug/incremental".to_owned(),
+                    SweepReason::Incremental
+                ),
+                (
+                    "target/x86_64-pc-windows-gnu/release".to_owned(),
+                    SweepReason::StaleProfile
+                ),
+            ]
+        );
+        assert!(sweep.reclaimable() < sweep.target_size);
+
+        sweep.clean();
+        assert!(td.0.join("target/debug/deps/app-1").exists());
+        assert!(!td.0.join("target/debug/incremental").exists());
+        assert!(!td.0.join("target/x86_64-pc-windows-gnu/release").exists());
+        // Nothing tells how old it is
+        assert!(td.0.join("target/release/deps/app-4").exists());
+    }
+
+    #[test]
+    fn cargo_sweep_toolchains() {
+        use super::{CargoEnv, SweepOptions, SweepReason};
+
+        let td = TestDir::new("cargo-sweep-toolchains");
+        td.file(
+            "rustup/toolchains/stable/lib/rustlib/multirust-channel-manifest.toml",
+            "[pkg.rustc]\nversion = \"1.80.0 (051478957 2024-07-21)\"\n",
+        )
+        .file("target/debug/.fingerprint/app-1/bin-app.json", "{}")
+        .file("target/debug/deps/app-1", "binary");
+
+        let rustc_info = |sysroot: &Path| {
+            let version = "rustc 1.76.0 (07dca489a 2024-02-04)\nrelease: 1.76.0\n";
+            let cfg = format!(
+                "___\nlib___.rlib\n{}\ndebug_assertions\n",
+                sysroot.display()
+            );
+            let rustc_info = serde_json::json!({
+                "outputs": { "1": { "stdout": version }, "2": { "stdout": cfg } }
+            });
+            fs::write(td.0.join("target/.rustc_info.json"), rustc_info.to_string()).unwrap();
+        };
+        let options = SweepOptions {
+            incremental: false,
+            env: CargoEnv(
+                [("RUSTUP_HOME".to_string(), td.0.join("rustup").into())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..SweepOptions::default()
+        };
+        let reasons = || -> Vec<SweepReason> {
+            super::sweep(&td.0.join("target"), &options)
+                .items
+                .iter()
+                .map(|i| i.reason)
+                .collect()
+        };
+
+        // Not installed through rustup, so it can't be told whether it's still around
+        rustc_info(Path::new("/usr"));
+        assert_eq!(reasons(), []);
+
+        rustc_info(&td.0.join("rustup/toolchains/1.76.0"));
+        assert_eq!(reasons(), [SweepReason::UninstalledToolchain]);
+    }
+
+    #[test]
+    fn python_detection() {
+        use super::scan;
+
+        let td = TestDir::new("python-detection");
+        td.file("pkg/pyproject.toml", "")
+            .file("pkg/venv/pyvenv.cfg", "")
+            .file("pkg/venv/lib/python3.12/site-packages/six.py", "")
+            .file("pkg/src/pkg.egg-info/PKG-INFO", "")
+            .dir("pkg/build")
+            .dir("pkg/.hypothesis")
+            .file("scripts/tool.py", "")
+            .dir("scripts/build")
+            .dir("scripts/__pycache__")
+            .file("rust/build.py", "")
+            .file("rust/Cargo.toml", "")
+            .file("reqs/requirements-dev.txt", "");
+
+        let mut projects: Vec<(PathBuf, &str, Vec<String>)> = scan(&td.0, &ScanOptions::default())
+            .filter_map(|p| p.ok())
+            .map(|p| {
+                let path = p.path.strip_prefix(&td.0).unwrap().to_path_buf();
+                (path, p.type_name(), relative_artifact_paths(&p))
+            })
+            .collect();
+        projects.sort();
+
+        assert_eq!(
+            projects,
+            [
+                (
+                    "pkg".into(),
+                    "Python",
+                    vec![".hypothesis", "build", "src/pkg.egg-info", "venv"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect()
+                ),
+                ("reqs".into(), "Python", vec![]),
+                ("rust".into(), "Cargo", vec![]),
+                (
+                    "scripts".into(),
+                    "Python (scripts)",
+                    vec!["__pycache__".into()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn node_tooling_dirs() {
+        let td = TestDir::new("node-tooling-dirs");
+        td.file(
+            "package.json",
+            r#"{
+                "dependencies": { "next": "14.0.0" },
+                "devDependencies": { "@storybook/react": "8.0.0" }
+            }"#,
+        )
+        .file("yarn.lock", "")
+        .file(".yarnrc.yml", "")
+        .dir("node_modules")
+        .dir(".next")
+        .dir("storybook-static")
+        .dir(".yarn/cache")
+        .dir(".yarn/releases")
+        .dir(".nuxt")
+        .dir(".cache")
+        .dir(".pnpm-store");
+
+        let project = Project {
+            project_type: ProjectType::Node,
+            path: td.0.clone(),
+        };
+
+        assert_eq!(
+            relative_artifact_paths(&project),
+            [".next", ".yarn/cache", "node_modules", "storybook-static"]
+        );
+    }
+
+    #[test]
+    fn build_trees() {
+        let td = TestDir::new("build-trees");
+        let source = td.0.join("src").canonicalize().unwrap_or(td.0.join("src"));
+        td.file("src/CMakeLists.txt", "")
+            .file(
+                "src/build-asan/CMakeCache.txt",
+                &format!("CMAKE_HOME_DIRECTORY:INTERNAL={}\n", source.display()),
+            )
+            .file("src/build-asan/CMakeFiles/x.o", "")
+            .file(
+                "src/out/clang-release/CMakeCache.txt",
+                "CMAKE_HOME_DIRECTORY:INTERNAL=/elsewhere\n",
+            )
+            .file("src/builddir/meson-private/coredata.dat", "")
+            .file(
+                "src/vpath/config.status",
+                "ac_pwd='/x/vpath'\nsrcdir='..'\n",
+            )
+            .file("src/ninja/build.ninja", "")
+            .file("src/obj/config.status", "");
+
+        let project = Project {
+            project_type: ProjectType::CMake,
+            path: td.0.join("src"),
+        };
+
+        assert_eq!(
+            relative_artifact_paths(&project),
+            ["build-asan", "builddir", "obj"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bazel_output_base() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let td = TestDir::new("bazel-output-base");
+        td.file("ws/MODULE.bazel", "")
+            .file(
+                "cache/_bazel_user/1/execroot/_main/bazel-out/k8-fastbuild/bin/app",
+                "",
+            )
+            .file(
+                "cache/_bazel_user/2/execroot/_main/bazel-out/k8-fastbuild/bin/other",
+                "",
+            )
+            .file(
+                "cache/unknown/execroot/_main/bazel-out/k8-fastbuild/bin/app",
+                "",
+            );
+        let workspace = td.0.join("ws").canonicalize().unwrap();
+        let output_base = td.0.join("cache/_bazel_user/1");
+        let other_base = td.0.join("cache/_bazel_user/2");
+        fs::write(
+            output_base.join("DO_NOT_BUILD_HERE"),
+            workspace.to_string_lossy().as_bytes(),
+        )
+        .unwrap();
+        fs::write(
+            other_base.join("DO_NOT_BUILD_HERE"),
+            "/some/other/workspace",
+        )
+        .unwrap();
+        symlink(
+            output_base.join("execroot/_main/bazel-out"),
+            workspace.join("bazel-out"),
+        )
+        .unwrap();
+        symlink(
+            output_base.join("execroot/_main"),
+            workspace.join("bazel-ws"),
+        )
+        .unwrap();
+        let read_only = output_base.join("execroot/_main/bazel-out/k8-fastbuild/bin");
+        fs::set_permissions(&read_only, fs::Permissions::from_mode(0o555)).unwrap();
+
+        let project = Project {
+            project_type: ProjectType::Bazel,
+            path: workspace.clone(),
+        };
+        assert_eq!(
+            project.artifact_paths(&ScanOptions::default()),
+            [output_base.canonicalize().unwrap()]
+        );
+
+        project.clean();
+        assert!(!output_base.exists());
+        assert!(other_base.exists());
+        assert!(!workspace.join("bazel-out").exists());
+        assert!(workspace.join("MODULE.bazel").exists());
+
+        // Pointing at another workspace's output base
+        symlink(
+            other_base.join("execroot/_main"),
+            workspace.join("bazel-ws"),
+        )
+        .unwrap();
+        assert!(project.artifact_paths(&ScanOptions::default()).is_empty());
+
+        // Anything with an execroot that isn't recorded as belonging to the workspace
+        fs::remove_file(workspace.join("bazel-ws")).unwrap();
+        symlink(
+            td.0.join("cache/unknown/execroot/_main"),
+            workspace.join("bazel-ws"),
+        )
+        .unwrap();
+        assert!(project.artifact_paths(&ScanOptions::default()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn nix_artifacts() {
+        use super::{artifact_size, scan};
+        use std::os::unix::fs::symlink;
+
+        let td = TestDir::new("nix-artifacts");
+        td.file("store/abc-app/bin/app", "0123456789")
+            .file("rust/Cargo.toml", "")
+            .file("rust/flake.nix", "")
+            .file("rust/.direnv/flake-profile-1-link", "")
+            .file("shell/shell.nix", "")
+            .file("python/.envrc", "layout python\n")
+            .file("python/.direnv/python-3.12/bin/python", "")
+            .file("go/go.mod", "")
+            .file("go/.envrc", "use flake ~/flakes/go\n")
+            .file("go/.direnv/flake-profile-1-link", "");
+        symlink(td.0.join("store/abc-app"), td.0.join("rust/result")).unwrap();
+        symlink(td.0.join("store/abc-app"), td.0.join("rust/result-man")).unwrap();
+        symlink(td.0.join("store/gone"), td.0.join("rust/result-old")).unwrap();
+
+        let mut projects: Vec<(String, &str)> = scan(&td.0, &ScanOptions::default())
+            .filter_map(|p| p.ok())
+            .map(|p| {
+                (
+                    p.path.file_name().unwrap().to_string_lossy().into(),
+                    p.type_name(),
+                )
+            })
+            .collect();
+        projects.sort();
+        // direnv on its own isn't Nix
+        assert_eq!(
+            projects,
+            [
+                ("go".into(), "Go"),
+                ("rust".into(), "Cargo"),
+                ("shell".into(), "Nix")
+            ]
+        );
+        let go = Project {
+            project_type: ProjectType::Go,
+            path: td.0.join("go"),
+        };
+        assert_eq!(relative_artifact_paths(&go), [".direnv"]);
+
+        let project = Project {
+            project_type: ProjectType::Cargo,
+            path: td.0.join("rust"),
+        };
+        assert_eq!(
+            relative_artifact_paths(&project),
+            [".direnv", "result", "result-man"]
+        );
+        assert_eq!(
+            artifact_size(&td.0.join("rust/result"), &ScanOptions::default()),
+            10
+        );
+        assert_eq!(project.size_dirs(&ScanOptions::default()).artifact_size, 20);
+        // Artifacts found once give the same breakdown
+        let artifacts = project.artifact_sizes(&ScanOptions::default());
+        assert_eq!(artifacts.iter().map(|(_, size)| size).sum::<u64>(), 20);
+        let dir_sizes = project.size_dirs_of(artifacts, &ScanOptions::default());
+        assert_eq!(dir_sizes.artifact_size, 20);
+
+        project.clean();
+        assert!(!td.0.join("rust/result").exists());
+        assert!(!td.0.join("rust/.direnv").exists());
+        assert!(td.0.join("store/abc-app/bin/app").exists());
+    }
+
+    #[test]
+    fn ecosystem_fixtures() {
+        use super::{project_type_from_file, scan};
+
+        // Name, files with optional contents after a `|`, directories, expected type and artifacts
+        type Case = (
+            &'static str,
+            &'static [&'static str],
+            &'static [&'static str],
+            &'static str,
+            &'static [&'static str],
+        );
+        let cases: [Case; 13] = [
+            (
+                "unity",
+                &["ProjectSettings/ProjectVersion.txt", "Assets/Scene.unity"],
+                &["Library", "Temp"],
+                "Unity",
+                &["Library", "Temp"],
+            ),
+            (
+                "unity-ide",
+                &["Assembly-CSharp.csproj", "Game.sln"],
+                &["Library", "obj"],
+                "Unity",
+                &["Library"],
+            ),
+            (
+                "unity-ide-projects",
+                &[
+                    "Assembly-CSharp.csproj",
+                    "Assembly-CSharp-Editor.csproj",
+                    "Game.Tests.csproj",
+                    "Game.sln",
+                ],
+                &["Library", "obj"],
+                "Unity",
+                &["Library"],
+            ),
+            (
+                "godot-3",
+                &["project.godot|config_version=4", "Main.cs", "Game.csproj"],
+                &[".import", ".godot"],
+                "Godot 3.x",
+                &[".import"],
+            ),
+            (
+                "godot-4",
+                &["Game.csproj", "project.godot"],
+                &[".godot"],
+                "Godot 4.x",
+                &[".godot"],
+            ),
+            (
+                "ruby",
+                &["Gemfile", "vendor/bundle/ruby/gem.rb"],
+                &[".bundle", "tmp/cache", "tmp/pids"],
+                "Ruby",
+                &[".bundle", "tmp/cache", "vendor/bundle"],
+            ),
+            (
+                "go",
+                &["go.mod"],
+                &["vendor", "bin", "cmd"],
+                "Go",
+                &["bin", "vendor"],
+            ),
+            (
+                "dune",
+                &["dune-project"],
+                &["_build", "_opam"],
+                "Dune",
+                &["_build", "_opam"],
+            ),
+            (
+                "rebar3",
+                &["rebar.config"],
+                &["_build", "src"],
+                "Rebar3",
+                &["_build"],
+            ),
+            (
+                "julia",
+                &["Project.toml"],
+                &["docs/build", "src"],
+                "Julia",
+                &["docs/build"],
+            ),
+            (
+                "renv",
+                &["renv.lock"],
+                &["renv/library", "R"],
+                "renv",
+                &["renv/library"],
+            ),
+            (
+                "latex",
+                &[
+                    "thesis.tex",
+                    "thesis.aux",
+                    "thesis.log",
+                    "thesis.pdf",
+                    "notes.log",
+                ],
+                &["_minted-thesis", "figures"],
+                "LaTeX",
+                &["_minted-thesis", "thesis.aux", "thesis.log"],
+            ),
+            ("latexmk", &[".latexmkrc", "main.fls"], &[], "LaTeX", &[]),
+        ];
+
+        for (name, files, dirs, type_name, artifacts) in cases {
+            let td = TestDir::new(&format!("ecosystem-{name}"));
+            for file in files {
+                let (file, contents) = file.split_once('|').unwrap_or((file, ""));
+                td.file(file, contents);
+            }
+            for dir in dirs {
+                td.dir(dir);
+            }
+
+            let projects: Vec<Project> = scan(&td.0, &ScanOptions::default())
+                .filter_map(|p| p.ok())
+                .collect();
+            assert_eq!(projects.len(), 1, "{name}");
+            assert_eq!(projects[0].type_name(), type_name, "{name}");
+            assert_eq!(relative_artifact_paths(&projects[0]), artifacts, "{name}");
+
+            projects[0].clean();
+            for artifact in artifacts {
+                assert!(!td.0.join(artifact).exists(), "{name} {artifact}");
+            }
+        }
+
+        // Whichever of a Unity project's .csproj files is seen first
+        let td = TestDir::new("ecosystem-unity-csproj");
+        td.file("Assembly-CSharp.csproj", "")
+            .file("Game.Tests.csproj", "");
+        for file in ["Assembly-CSharp.csproj", "Game.Tests.csproj"] {
+            assert_eq!(
+                project_type_from_file(&td.0, file),
+                Some(ProjectType::Unity),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn artifact_verification() {
+        let td = TestDir::new("artifact-verification");
+        td.file("cmake/CMakeLists.txt", "")
+            .file("cmake/build/CMakeCache.txt", "")
+            .file("scripts/CMakeLists.txt", "")
+            .file("scripts/build/bootstrap.sh", "")
+            .file("php/composer.json", "")
+            .file("php/vendor/autoload.php", "")
+            .file("dotnet/App.csproj", "")
+            .file("dotnet/bin/Debug/net8.0/App.dll", "")
+            .file("dotnet/obj/project.assets.json", "")
+            .file("node/package.json", "")
+            .file("node/node_modules/.package-lock.json", "");
+
+        let verified = |project_type, name: &str| {
+            let project = Project {
+                project_type,
+                path: td.0.join(name),
+            };
+            let mut paths: Vec<(String, bool)> = project
+                .artifact_paths(&ScanOptions::default())
+                .iter()
+                .map(|p| {
+                    (
+                        project.relative_display(p).replace('\\', "/"),
+                        project.is_verified_artifact(p),
+                    )
+                })
+                .collect();
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(
+            verified(ProjectType::CMake, "cmake"),
+            [("build".into(), true)]
+        );
+        assert_eq!(
+            verified(ProjectType::CMake, "scripts"),
+            [("build".into(), false)]
+        );
+        assert_eq!(
+            verified(ProjectType::Composer, "php"),
+            [("vendor".into(), false)]
+        );
+        assert_eq!(
+            verified(ProjectType::Dotnet, "dotnet"),
+            [("bin".into(), true), ("obj".into(), true)]
+        );
+        assert_eq!(
+            verified(ProjectType::Node, "node"),
+            [("node_modules".into(), true)]
+        );
+
+        let scripts = Project {
+            project_type: ProjectType::CMake,
+            path: td.0.join("scripts"),
+        };
+        scripts
+            .clean_with(
+                &ScanOptions::default(),
+                &CleanOptions {
+                    verified_only: true,
+                    ..CleanOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(td.0.join("scripts/build/bootstrap.sh").exists());
+    }
+
+    #[test]
+    fn clean_refusal() {
+        let td = TestDir::new("clean-refusal");
+        td.file("Cargo.toml", "")
+            .file("target/debug/app", "")
+            .file("vendor/Cargo.toml", "")
+            .file("composer.json", "")
+            .file("vendor/org/package/.git/HEAD", "");
+        let project = |project_type| Project {
+            project_type,
+            path: td.0.clone(),
+        };
+
+        let cargo = project(ProjectType::Cargo);
+        assert_eq!(cargo.clean_refusal(&td.0.join("target")), None);
+
+        let composer = project(ProjectType::Composer);
+        let vendor = td.0.join("vendor");
+        assert_eq!(
+            composer.clean_refusal(&vendor),
+            Some(CleanRefusal::NestedProject("Cargo"))
+        );
+        fs::remove_file(vendor.join("Cargo.toml")).unwrap();
+        assert_eq!(
+            composer.clean_refusal(&vendor),
+            Some(CleanRefusal::NestedRepository(
+                vendor.join("org/package/.git")
+            ))
+        );
+
+        composer.clean();
+        assert!(vendor.exists());
+        composer
+            .clean_with(
+                &ScanOptions::default(),
+                &CleanOptions {
+                    allow_nested: true,
+                    ..CleanOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(!vendor.exists());
+    }
+
+    #[test]
+    fn cachedir_tags() {
+        use super::{scan, scan_tagged_caches};
+
+        const TAG: &str = "Signature: 8a477f597d28d172789f06886806bc55\n# generated by a tool\n";
+
+        let td = TestDir::new("cachedir-tags");
+        td.file("app/CMakeLists.txt", "")
+            .file("app/.tool-cache/CACHEDIR.TAG", TAG)
+            .file("app/build/CACHEDIR.TAG", TAG)
+            .file("app/notes/CACHEDIR.TAG", "not a signature")
+            .file("app/build/vendored/package.json", "")
+            .file("app/src/gen/.cache/CACHEDIR.TAG", TAG)
+            .file("app/src/gen/.cache/inner/CACHEDIR.TAG", TAG)
+            .file("app/tools/lint/package.json", "")
+            .file("app/tools/lint/.cache/CACHEDIR.TAG", TAG)
+            .file("home/.cache/pip/CACHEDIR.TAG", TAG)
+            .file("home/.cache/pip/http/package.json", "")
+            .file("home/.cache/other/data", "");
+
+        let app = Project {
+            project_type: ProjectType::CMake,
+            path: td.0.join("app"),
+        };
+        // Nested caches count, a nested project's caches are its own
+        assert_eq!(
+            relative_artifact_paths(&app),
+            [".tool-cache", "build", "src/gen/.cache"]
+        );
+        assert!(app.is_verified_artifact(&td.0.join("app/build")));
+
+        // Projects aren't looked for within tagged caches
+        let projects: Vec<PathBuf> = scan(&td.0, &ScanOptions::default())
+            .filter_map(|p| p.ok())
+            .map(|p| p.path)
+            .collect();
+        assert_eq!(projects, [td.0.join("app")]);
+
+        let caches: Vec<PathBuf> = scan_tagged_caches(&td.0, &ScanOptions::default()).collect();
+        assert_eq!(caches, [td.0.join("home/.cache/pip")]);
+    }
+
+    #[test]
+    fn cachedir_tags_in_glob_artifacts() {
+        use super::dir_size;
+
+        const TAG: &str = "Signature: 8a477f597d28d172789f06886806bc55\n";
+
+        let td = TestDir::new("cachedir-tags-in-globs");
+        td.file("web/package.json", "{}")
+            .file("web/node_modules/.cache/babel/CACHEDIR.TAG", TAG)
+            .file("web/node_modules/.cache/babel/data", "0123456789")
+            .file("web/src/.cache/CACHEDIR.TAG", TAG);
+
+        let web = Project {
+            project_type: ProjectType::Node,
+            path: td.0.join("web"),
+        };
+        // Caches inside `**/node_modules` are part of it, not artifacts of their own
+        assert_eq!(
+            relative_artifact_paths(&web),
+            ["node_modules", "src/.cache"]
+        );
+        let node_modules = dir_size(&td.0.join("web/node_modules"), &ScanOptions::default());
+        let src_cache = dir_size(&td.0.join("web/src/.cache"), &ScanOptions::default());
+        assert_eq!(web.size(&ScanOptions::default()), node_modules + src_cache);
+    }
+
+    /// A git index of `version` tracking `paths`, with the fields kondo doesn't read zeroed
+    fn git_index(version: u32, paths: &[&str]) -> Vec<u8> {
+        let mut index = b"DIRC".to_vec();
+        index.extend_from_slice(&version.to_be_bytes());
+        index.extend_from_slice(&(paths.len() as u32).to_be_bytes());
+        let mut previous = "";
+        for path in paths {
+            let start = index.len();
+            index.extend_from_slice(&[0; 40 + 20]);
+            index.extend_from_slice(&(path.len() as u16).to_be_bytes());
+            if version == 4 {
+                let common = previous
+                    .bytes()
+                    .zip(path.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                index.push((previous.len() - common) as u8);
+                index.extend_from_slice(&path.as_bytes()[common..]);
+                index.push(0);
+            } else {
+                index.extend_from_slice(path.as_bytes());
+                let len = index.len() - start;
+                index.resize(start + (len + 8) / 8 * 8, 0);
+            }
+            previous = path;
+        }
+        index
+    }
+
+    #[test]
+    fn gitignored_artifacts() {
+        use super::scan;
+
+        let td = TestDir::new("gitignored");
+        td.file("repo/.gitignore", "out/\ncoverage/\nforced/\n")
+            .file("repo/src/main.c", "int main() {}")
+            .file("repo/out/debug/main", "0123456789")
+            .file("repo/coverage/lcov.info", "0")
+            .file("repo/forced/keep", "0123456789")
+            .file("repo/app/Cargo.toml", "")
+            .file("repo/app/target/debug/app", "0123456789")
+            .file("repo/libs/dep/.gitignore", "build/\n")
+            .file("repo/libs/dep/build/lib.a", "0123456789")
+            .file("repo/libs/dep/.git/index", "");
+        let tracked = [".gitignore", "app/Cargo.toml", "forced/keep", "src/main.c"];
+
+        let options = ScanOptions {
+            gitignored_min_size: Some(5),
+            ..ScanOptions::default()
+        };
+        let projects = |options: &ScanOptions| {
+            let mut projects: Vec<(String, ProjectType)> = scan(&td.0, options)
+                .filter_map(|p| p.ok())
+                .map(|p| (p.relative_display(&td.0), p.project_type))
+                .collect();
+            projects.sort_by(|a, b| a.0.cmp(&b.0));
+            projects
+        };
+
+        // Only an opt-in
+        td.file("repo/.git/index", "");
+        assert_eq!(projects(&ScanOptions::default()).len(), 1);
+        assert_eq!(projects(&options).len(), 3);
+
+        let repo = Project {
+            project_type: ProjectType::Gitignored,
+            path: td.0.join("repo"),
+        };
+        for version in [2, 3, 4] {
+            fs::write(td.0.join("repo/.git/index"), git_index(version, &tracked)).unwrap();
+            let mut paths: Vec<String> = repo
+                .artifact_paths(&options)
+                .iter()
+                .map(|p| repo.relative_display(p))
+                .collect();
+            paths.sort();
+            assert_eq!(paths, ["out"], "index version {version}");
+        }
+        assert!(!repo.is_verified_artifact(&td.0.join("repo/out")));
+
+        // The nested repository's ignored directories are only its own
+        fs::write(
+            td.0.join("repo/libs/dep/.git/index"),
+            git_index(2, &[".gitignore"]),
+        )
+        .unwrap();
+        let dep = Project {
+            project_type: ProjectType::Gitignored,
+            path: td.0.join("repo/libs/dep"),
+        };
+        assert_eq!(
+            dep.artifact_paths(&options),
+            [td.0.join("repo/libs/dep/build")]
+        );
+
+        // Cleaning removes what was found with the same threshold
+        repo.clean_with(&options, &CleanOptions::default()).unwrap();
+        assert!(!td.0.join("repo/out").exists());
+        assert!(td.0.join("repo/coverage/lcov.info").exists());
+        assert!(td.0.join("repo/forced/keep").exists());
+
+        // Most entries of a split index are in a shared index file
+        let mut index = git_index(2, &[".gitignore"]);
+        index.extend_from_slice(b"link");
+        index.extend_from_slice(&20u32.to_be_bytes());
+        // The shared index' name and the checksum
+        index.extend_from_slice(&[0; 20 + 20]);
+        fs::write(td.0.join("repo/.git/index"), index).unwrap();
+        assert!(repo.artifact_paths(&options).is_empty());
+
+        // An index that can't be read isn't taken as nothing being tracked
+        td.file("repo/.git/index", "DIRC");
+        assert!(repo.artifact_paths(&options).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn artifact_escapes() {
+        use super::{ArtifactEscape, LinkTargetRefusal};
+        use std::os::unix::fs::symlink;
+
+        let td = TestDir::new("artifact-escapes");
+        td.file(
+            "shared/node_modules/pkg/index.js",
+            &"0123456789".repeat(100),
+        )
+        .file("shared/node_modules/.package-lock.json", "{}")
+        .file("shared/data/file", "")
+        .file("node/package.json", "")
+        .file("rust/Cargo.toml", "")
+        .file("rust/target/debug/app", "");
+        symlink(
+            td.0.join("shared/node_modules"),
+            td.0.join("node/node_modules"),
+        )
+        .unwrap();
+        symlink(td.0.join("shared/data"), td.0.join("rust/target/data")).unwrap();
+
+        let node = Project {
+            project_type: ProjectType::Node,
+            path: td.0.join("node"),
+        };
+        let rust = Project {
+            project_type: ProjectType::Cargo,
+            path: td.0.join("rust"),
+        };
+        let shared = fs::canonicalize(td.0.join("shared")).unwrap();
+        assert_eq!(
+            node.artifact_escape(&td.0.join("node/node_modules")),
+            Some(ArtifactEscape::Symlink(shared.join("node_modules")))
+        );
+        assert_eq!(
+            rust.artifact_escape(&td.0.join("rust/target")),
+            Some(ArtifactEscape::NestedSymlink(
+                td.0.join("rust/target/data"),
+                shared.join("data")
+            ))
+        );
+        // What cleaning frees
+        let link_len = fs::symlink_metadata(td.0.join("node/node_modules"))
+            .unwrap()
+            .len();
+        assert_eq!(node.size(&ScanOptions::default()), link_len);
+
+        // Only the links go
+        node.clean();
+        rust.clean();
+        assert!(!td.0.join("node/node_modules").exists());
+        assert!(!td.0.join("rust/target").exists());
+        assert!(td.0.join("shared/node_modules/pkg/index.js").exists());
+        assert!(td.0.join("shared/data/file").exists());
+
+        symlink(
+            td.0.join("shared/node_modules"),
+            td.0.join("node/node_modules"),
+        )
+        .unwrap();
+        node.clean_with(
+            &ScanOptions::default(),
+            &CleanOptions {
+                remove_link_targets: true,
+                ..CleanOptions::default()
+            },
+        )
+        .unwrap();
+        assert!(!td.0.join("shared/node_modules").exists());
+
+        // Targets holding the project or not looking like the artifact are kept
+        let remove_link_targets = CleanOptions {
+            remove_link_targets: true,
+            ..CleanOptions::default()
+        };
+        let link = td.0.join("node/node_modules");
+        let root = fs::canonicalize(&td.0).unwrap();
+        symlink(&td.0, &link).unwrap();
+        assert_eq!(
+            node.link_target_refusal(&link, &root),
+            Some(LinkTargetRefusal::Protected)
+        );
+        assert_eq!(
+            node.link_target_refusal(&link, Path::new("/")),
+            Some(LinkTargetRefusal::Protected)
+        );
+        node.clean_with(&ScanOptions::default(), &remove_link_targets)
+            .unwrap();
+        assert!(!link.exists());
+        assert!(td.0.join("node/package.json").exists());
+
+        symlink(td.0.join("shared/data"), &link).unwrap();
+        assert_eq!(
+            node.link_target_refusal(&link, &shared.join("data")),
+            Some(LinkTargetRefusal::Unverified)
+        );
+        node.clean_with(&ScanOptions::default(), &remove_link_targets)
+            .unwrap();
+        assert!(td.0.join("shared/data/file").exists());
+    }
+
+    #[test]
+    fn mount_points() {
+        use super::{
+            mount_of,
+            mounts::{parse_mountinfo, skipped_mount_points},
+        };
+
+        let mounts = parse_mountinfo(
+            "22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw\n\
+             23 22 0:21 / /proc rw,nosuid shared:12 - proc proc rw\n\
+             24 22 259:3 / /home rw,relatime shared:2 master:1 - btrfs /dev/nvme0n1p3 rw\n\
+             25 24 0:45 / /home/me/remote\\040box rw - fuse.sshfs me@box:/ rw\n\
+             26 24 0:46 / /home/me/nas rw - nfs4 nas:/export rw\n\
+             27 24 259:3 /me/code /home/me/code-bind rw - btrfs /dev/nvme0n1p3 rw\n",
+        );
+        assert_eq!(mounts.len(), 6);
+        assert_eq!(mounts[3].mount_point, PathBuf::from("/home/me/remote box"));
+        assert_eq!(mounts[3].fs_type, "fuse.sshfs");
+        assert_eq!(mounts[4].source, "nas:/export");
+
+        let mount = mount_of(&mounts, Path::new("/home/me/code-bind/app/target")).unwrap();
+        assert_eq!(mount.device, "259:3");
+        assert_eq!(
+            mount_of(&mounts, Path::new("/etc")).unwrap().fs_type,
+            "ext4"
+        );
+
+        let skipped = |root: &str, options: &ScanOptions| {
+            skipped_mount_points(&mounts, Path::new(root), options)
+        };
+        assert_eq!(
+            skipped("/", &ScanOptions::default()),
+            [
+                PathBuf::from("/proc"),
+                PathBuf::from("/home/me/remote box"),
+                PathBuf::from("/home/me/nas")
+            ]
+        );
+        // A skipped filesystem is still scanned when it's the root
+        assert!(skipped("/home/me/nas", &ScanOptions::default()).is_empty());
+
+        let options = ScanOptions {
+            allowed_filesystems: vec!["nfs4".into()],
+            denied_filesystems: vec!["btrfs".into()],
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            skipped("/home", &options),
+            [
+                PathBuf::from("/home/me/remote box"),
+                PathBuf::from("/home/me/code-bind")
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn overlapping_roots() {
+        use super::{normalise_roots, scan_roots};
+        use std::os::unix::fs::symlink;
+
+        let td = TestDir::new("overlapping-roots");
+        td.file("src/app/Cargo.toml", "")
+            .file("src/work/site/package.json", "")
+            .file("other/tool/go.mod", "");
+        symlink(td.0.join("src/work"), td.0.join("work-link")).unwrap();
+        symlink(td.0.join("src/work"), td.0.join("other/work")).unwrap();
+        let root = fs::canonicalize(&td.0).unwrap();
+
+        assert_eq!(
+            normalise_roots(&[
+                td.0.join("src/work"),
+                td.0.join("other"),
+                td.0.join("src"),
+                td.0.join("work-link"),
+                td.0.join("src/../src"),
+            ]),
+            [root.join("other"), root.join("src")]
+        );
+
+        let projects = |options: &ScanOptions| {
+            let mut projects: Vec<PathBuf> =
+                scan_roots(&[td.0.join("other"), td.0.join("src")], options)
+                    .filter_map(|p| p.ok())
+                    .map(|p| p.path)
+                    .collect();
+            projects.sort();
+            projects
+        };
+        assert_eq!(projects(&ScanOptions::default()).len(), 3);
+        // `other/work` leads to the projects in `src/work` too
+        let follow_symlinks = ScanOptions {
+            follow_symlinks: true,
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            projects(&follow_symlinks),
+            [
+                root.join("other/tool"),
+                root.join("other/work/site"),
+                root.join("src/app")
+            ]
+        );
+    }
+
+    #[test]
+    fn hidden_dirs() {
+        use super::scan;
+
+        let td = TestDir::new("hidden-dirs");
+        td.file("app/Cargo.toml", "")
+            .file(".config/tool/package.json", "")
+            .file(".worktrees/feature/Cargo.toml", "")
+            .file(".cache/pkg/package.json", "")
+            .file(".Trash-1000/files/old/Cargo.toml", "")
+            .file(".local/share/Trash/files/older/Cargo.toml", "")
+            .file(".local/share/nvim/lazy/plugin/Makefile", "")
+            .file(".local/share/nvim/lazy/plugin/CMakeLists.txt", "")
+            .file(".cargo/registry/src/index/serde-1.0.0/Cargo.toml", "")
+            .file(
+                ".rustup/toolchains/stable/lib/rustlib/src/rust/library/std/Cargo.toml",
+                "",
+            )
+            .file(".npm/_npx/1234/package.json", "")
+            .file(".m2/repository/org/example/lib/1.0/pom.xml", "")
+            .file(".gradle/caches/transforms/build.gradle", "");
+
+        let projects = |options: &ScanOptions| {
+            let mut projects: Vec<String> = scan(&td.0, options)
+                .filter_map(|p| p.ok())
+                .map(|p| {
+                    p.path
+                        .strip_prefix(&td.0)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect();
+            projects.sort();
+            projects
+        };
+
+        assert_eq!(projects(&ScanOptions::default()), ["app"]);
+        let include_hidden = ScanOptions {
+            include_hidden: true,
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            projects(&include_hidden),
+            [
+                ".config/tool",
+                ".local/share/nvim/lazy/plugin",
+                ".worktrees/feature",
+                "app"
+            ]
+        );
+
+        // A hidden root is scanned either way
+        let config = td.0.join(".config");
+        assert_eq!(scan(&config, &ScanOptions::default()).count(), 1);
+    }
+
+    #[test]
+    fn build_locks() {
+        use super::InUse;
+
+        let td = TestDir::new("build-locks");
+        td.file("app/Cargo.toml", "")
+            .file("app/target/debug/.cargo-lock", "")
+            .file("app/target/debug/app", "");
+        let app = Project {
+            project_type: ProjectType::Cargo,
+            path: td.0.join("app"),
+        };
+        let lock_path = td.0.join("app/target/debug/.cargo-lock");
+
+        assert_eq!(app.in_use(), None);
+
+        let lock = fs::File::open(&lock_path).unwrap();
+        lock.lock().unwrap();
+        assert_eq!(app.in_use(), Some(InUse::BuildLock(lock_path)));
+        assert!(app
+            .clean_with(&ScanOptions::default(), &CleanOptions::default())
+            .is_err());
+        assert!(td.0.join("app/target").exists());
+
+        // Released once cargo is done
+        drop(lock);
+        assert_eq!(app.in_use(), None);
+        app.clean_with(&ScanOptions::default(), &CleanOptions::default())
+            .unwrap();
+        assert!(!td.0.join("app/target").exists());
+    }
+
+    #[test]
+    fn target_dir_locks() {
+        use super::{InUse, Processes, Sweep, SweepItem, SweepReason, TargetDir};
+
+        let td = TestDir::new("target-dir-locks");
+        td.file("shared/debug/.cargo-lock", "")
+            .file("shared/debug/app", "")
+            .file("shared/release/app", "");
+        let target_dir = TargetDir {
+            path: td.0.join("shared"),
+            shared: true,
+        };
+        let sweep = Sweep {
+            target_dir: td.0.join("shared"),
+            target_size: 0,
+            items: vec![SweepItem {
+                path: td.0.join("shared/release"),
+                size: 0,
+                reason: SweepReason::StaleProfile,
+            }],
+        };
+        let lock_path = td.0.join("shared/debug/.cargo-lock");
+
+        // Found whatever the target directory is called
+        let lock = fs::File::open(&lock_path).unwrap();
+        lock.lock().unwrap();
+        assert_eq!(
+            target_dir.in_use(),
+            Some(InUse::BuildLock(lock_path.clone()))
+        );
+        assert_eq!(sweep.in_use(), Some(InUse::BuildLock(lock_path)));
+        assert!(sweep
+            .clean_with(&CleanOptions::default(), &Processes::default())
+            .is_err());
+        assert!(td.0.join("shared/release").exists());
+        assert!(target_dir
+            .clean_with(&CleanOptions::default(), &Processes::default())
+            .is_err());
+        assert!(td.0.join("shared").exists());
+
+        drop(lock);
+        sweep
+            .clean_with(&CleanOptions::default(), &Processes::default())
+            .unwrap();
+        assert!(!td.0.join("shared/release").exists());
+        target_dir
+            .clean_with(&CleanOptions::default(), &Processes::default())
+            .unwrap();
+        assert!(!td.0.join("shared").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn processes_in_use() {
+        use super::{InUse, Processes};
+        use std::process::Command;
+
+        let td = TestDir::new("processes-in-use");
+        td.file("app/package.json", "{}")
+            .file("app/node_modules/.package-lock.json", "{}")
+            .file("other/package.json", "{}");
+        let app = Project {
+            project_type: ProjectType::Node,
+            path: td.0.join("app"),
+        };
+        let other = Project {
+            project_type: ProjectType::Node,
+            path: td.0.join("other"),
+        };
+        let artifact_paths = app.artifact_paths(&ScanOptions::default());
+
+        let before = Processes::snapshot();
+        let mut server = Command::new("sleep")
+            .arg("30")
+            .current_dir(td.0.join("app"))
+            .spawn()
+            .unwrap();
+        let processes = Processes::snapshot();
+
+        // One snapshot serves every project, processes started since aren't in it
+        assert!(matches!(
+            app.in_use_with(&artifact_paths, &processes),
+            Some(InUse::Process { pid, .. }) if pid == server.id()
+        ));
+        assert_eq!(other.in_use_with(&[], &processes), None);
+        assert_eq!(app.in_use_with(&artifact_paths, &before), None);
+        assert!(app
+            .clean_artifacts(&artifact_paths, &CleanOptions::default(), &processes)
+            .is_err());
+        assert!(td.0.join("app/node_modules").exists());
+
+        server.kill().unwrap();
+        server.wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn gradle_locks() {
+        use super::InUse;
+        use std::os::fd::AsRawFd;
+
+        let td = TestDir::new("gradle-locks");
+        td.file("app/build.gradle", "")
+            .file("app/.gradle/8.5/checksums/checksums.lock", "");
+        let app = Project {
+            project_type: ProjectType::Gradle,
+            path: td.0.join("app"),
+        };
+        let lock_path = td.0.join("app/.gradle/8.5/checksums/checksums.lock");
+
+        // Gradle locks through Java's FileChannel.lock, an fcntl record lock that flock doesn't
+        // see
+        let lock = fs::File::options().write(true).open(&lock_path).unwrap();
+        let mut record = unsafe { std::mem::zeroed::<libc::flock>() };
+        record.l_type = libc::F_WRLCK as libc::c_short;
+        record.l_whence = libc::SEEK_SET as libc::c_short;
+        assert_eq!(
+            unsafe { libc::fcntl(lock.as_raw_fd(), libc::F_SETLK, &record) },
+            0
+        );
+        assert_eq!(app.in_use(), Some(InUse::BuildLock(lock_path)));
+
+        drop(lock);
+        assert_eq!(app.in_use(), None);
+    }
+
+    #[test]
+    fn nested_artifact_patterns() {
+        let td = TestDir::new("nested-artifact-patterns");
+        td.file("pyproject.toml", "")
+            .file("pkg/__init__.py", "")
+            .dir("pkg/__pycache__")
+            .dir("pkg/sub/__pycache__")
+            .dir("tests/.pytest_cache")
+            .dir(".mypy_cache")
+            .dir("__pycache__/__pycache__");
+
+        let project = Project {
+            project_type: ProjectType::Python,
+            path: td.0.clone(),
+        };
+
+        assert_eq!(
+            relative_artifact_paths(&project),
+            [
+                ".mypy_cache",
+                "__pycache__",
+                "pkg/__pycache__",
+                "pkg/sub/__pycache__",
+                "tests/.pytest_cache",
+            ]
+        );
+    }
 
     #[test]
     fn elapsed() {
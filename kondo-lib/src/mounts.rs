@@ -0,0 +1,130 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::ScanOptions;
+
+const FILE_MOUNTINFO: &str = "/proc/self/mountinfo";
+
+/// Filesystems that aren't entered below a scan root unless allowed through
+/// [`ScanOptions::allowed_filesystems`]. They either hold no projects, like `proc`, or are slow to
+/// walk and shared with other machines, like `nfs`.
+pub const DEFAULT_SKIPPED_FILESYSTEMS: [&str; 17] = [
+    "proc",
+    "sysfs",
+    "devtmpfs",
+    "devpts",
+    "cgroup",
+    "cgroup2",
+    "debugfs",
+    "tracefs",
+    "securityfs",
+    "fusectl",
+    "binfmt_misc",
+    "fuse.sshfs",
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "afs",
+];
+
+/// A mounted filesystem, as listed in `/proc/self/mountinfo`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    /// The `major:minor` device number, shared by bind mounts of the same filesystem
+    pub device: String,
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl Mount {
+    /// Whether the scan descends into this filesystem when it's found below a scan root
+    pub fn is_scanned(&self, options: &ScanOptions) -> bool {
+        let fs_type = self.fs_type.as_str();
+        !options.denied_filesystems.iter().any(|t| t == fs_type)
+            && (options.allowed_filesystems.iter().any(|t| t == fs_type)
+                || !DEFAULT_SKIPPED_FILESYSTEMS.contains(&fs_type))
+    }
+}
+
+/// The filesystems mounted for this process. Empty where `/proc/self/mountinfo` isn't available.
+pub fn mounts() -> Vec<Mount> {
+    fs::read_to_string(FILE_MOUNTINFO)
+        .map(|contents| parse_mountinfo(&contents))
+        .unwrap_or_default()
+}
+
+/// The mount that `path` is on, the one with the longest mount point containing it
+pub fn mount_of<'a>(mounts: &'a [Mount], path: &Path) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.components().count())
+}
+
+/// Mount points below `root` that the scan doesn't enter. The root itself is always scanned, as
+/// it was asked for.
+pub(crate) fn skipped_mount_points(
+    mounts: &[Mount],
+    root: &Path,
+    options: &ScanOptions,
+) -> Vec<PathBuf> {
+    mounts
+        .iter()
+        .filter(|m| m.mount_point.starts_with(root) && m.mount_point != root)
+        .filter(|m| !m.is_scanned(options))
+        .map(|m| m.mount_point.clone())
+        .collect()
+}
+
+/// Parses the lines of a mountinfo file, e.g.
+/// `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`
+/// See https://www.kernel.org/doc/Documentation/filesystems/proc.txt
+pub(crate) fn parse_mountinfo(contents: &str) -> Vec<Mount> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            // A variable number of optional fields come before the separator
+            let (fields, fs_fields) = line.split_once(" - ")?;
+            let mut fields = fields.split(' ');
+            let device = fields.nth(2)?;
+            let mount_point = fields.nth(1)?;
+            let mut fs_fields = fs_fields.split(' ');
+            let fs_type = fs_fields.next()?;
+            let source = fs_fields.next().unwrap_or_default();
+            Some(Mount {
+                device: device.to_string(),
+                mount_point: PathBuf::from(unescape(mount_point)),
+                fs_type: fs_type.to_string(),
+                source: unescape(source),
+            })
+        })
+        .collect()
+}
+
+/// Spaces, tabs, newlines and backslashes are escaped as octal, like `\040`
+fn unescape(field: &str) -> String {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let octal = tail
+            .get(..3)
+            .filter(|_| byte == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match octal {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[3..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Mutex, OnceLock},
+};
+
+use crate::DIR_NIX_STORE;
+
+/// Sizes already found by [`pinned_size`], by store path
+static PINNED_SIZES: OnceLock<Mutex<HashMap<PathBuf, Option<u64>>>> = OnceLock::new();
+/// The store paths every garbage collector root points to
+static ROOT_TARGETS: OnceLock<Option<Vec<PathBuf>>> = OnceLock::new();
+
+/// What removing the garbage collector root `link` lets `nix-collect-garbage` free: the store
+/// paths in the closure of its target that no other root keeps alive, with their sizes as
+/// recorded in the local Nix database. Roots pointing at the same store path count as one.
+pub(crate) fn pinned_size(link: &Path) -> Option<u64> {
+    let store_path = fs::read_link(link).ok()?;
+    if !store_path.starts_with(DIR_NIX_STORE) {
+        return None;
+    }
+
+    let cache = PINNED_SIZES.get_or_init(Default::default);
+    if let Some(size) = cache.lock().ok()?.get(&store_path) {
+        return *size;
+    }
+    let size = closure_only_pinned_by(&store_path);
+    cache.lock().ok()?.insert(store_path, size);
+    size
+}
+
+fn closure_only_pinned_by(store_path: &Path) -> Option<u64> {
+    let other_roots: Vec<&OsStr> = ROOT_TARGETS
+        .get_or_init(root_targets)
+        .as_ref()?
+        .iter()
+        .filter(|target| *target != store_path)
+        .map(|target| target.as_os_str())
+        .collect();
+
+    let closure = requisites(&[store_path.as_os_str()])?;
+    let kept_alive = if other_roots.is_empty() {
+        String::new()
+    } else {
+        requisites(&other_roots)?
+    };
+    let kept_alive: HashSet<&str> = kept_alive.lines().collect();
+    let pinned: Vec<&OsStr> = closure
+        .lines()
+        .filter(|path| !kept_alive.contains(path))
+        .map(OsStr::new)
+        .collect();
+    if pinned.is_empty() {
+        return Some(0);
+    }
+
+    let mut args = vec![OsStr::new("--size")];
+    args.extend(pinned);
+    let sizes = nix_store(&args)?;
+    Some(
+        sizes
+            .lines()
+            .filter_map(|s| s.trim().parse::<u64>().ok())
+            .sum(),
+    )
+}
+
+/// Store paths in the closure of `store_paths`, one per line
+fn requisites(store_paths: &[&OsStr]) -> Option<String> {
+    let mut args = vec![OsStr::new("--requisites")];
+    args.extend(store_paths);
+    nix_store(&args)
+}
+
+/// Targets of the lines `nix-store --gc --print-roots` prints, like
+/// `/home/user/app/result -> /nix/store/...-app`. Roots held by running processes can point at
+/// files inside a store path, they're reduced to the store path.
+fn root_targets() -> Option<Vec<PathBuf>> {
+    let output = Command::new("nix-store")
+        .args(["--gc", "--print-roots"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let mut targets: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.rsplit_once(" -> "))
+        .filter_map(|(_, target)| {
+            let name = Path::new(target).strip_prefix(DIR_NIX_STORE).ok()?;
+            Some(Path::new(DIR_NIX_STORE).join(name.components().next()?))
+        })
+        .collect();
+    targets.sort();
+    targets.dedup();
+    Some(targets)
+}
+
+fn nix_store(args: &[&OsStr]) -> Option<String> {
+    let output = Command::new("nix-store")
+        .arg("--query")
+        .args(args)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,24 +1,32 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::PathBuf,
-    sync::mpsc::{Receiver, Sender},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicU64, Ordering},
+        mpsc::{Receiver, Sender},
+    },
+    time::SystemTime,
 };
 
 use bevy::{
     ecs::system::RunSystemOnce,
     feathers::{
         FeathersPlugin,
-        controls::{ButtonProps, ButtonVariant, button},
+        controls::{ButtonProps, ButtonVariant, button, checkbox},
         dark_theme::create_dark_theme,
         theme::{ThemeBackgroundColor, ThemedText, UiTheme},
         tokens,
     },
+    input::{ButtonState, keyboard::KeyboardInput},
     input_focus::{
-        InputDispatchPlugin,
-        tab_navigation::{TabGroup, TabNavigationPlugin},
+        FocusedInput, InputDispatchPlugin,
+        tab_navigation::{TabGroup, TabIndex, TabNavigationPlugin},
     },
     prelude::*,
     tasks::{AsyncComputeTaskPool, Task, futures::check_ready},
-    ui_widgets::{Activate, UiWidgetsPlugins, observe},
+    ui::Checked,
+    ui_widgets::{Activate, Checkbox, UiWidgetsPlugins, ValueChange, observe},
 };
 
 pub(super) fn game_plugin(app: &mut App) {
@@ -53,9 +61,19 @@ pub(super) fn game_plugin(app: &mut App) {
         Update,
         (
             process_new_projects,
+            update_scan_status_ui,
+            update_filter_labels,
+            update_include_hidden_label,
             update_project_list_ui,
+            sync_selection_checkboxes,
+            update_select_type_buttons,
+            update_selection_summary,
+            update_type_summary,
+            update_left_pane_visibility,
+            update_treemap_ui,
             select_project_update,
             handle_clean_tasks,
+            update_bulk_clean_progress,
         )
             .chain(),
     );
@@ -66,29 +84,97 @@ pub(super) fn game_plugin(app: &mut App) {
 
     app.insert_resource(SelectedProject(None));
 
+    app.insert_resource(ScanStatus::Idle);
+
+    app.init_resource::<ProjectSelection>();
+
+    app.init_resource::<ProjectListView>();
+
+    app.init_resource::<ScanRoots>();
+
+    app.init_resource::<ScanSettings>();
+
+    app.init_resource::<LeftPane>();
+
+    app.init_resource::<BulkClean>();
+
     app.insert_non_send_resource(BackgroundThreadCommunication::default());
 }
 
 #[derive(Component)]
-struct CleanTask(Task<kondo_lib::Project>);
+struct CleanTask(Task<(kondo_lib::Project, Result<(), kondo_lib::InUse>)>);
+
+/// A snapshot of the running processes shared by the projects cleaned together, taken by
+/// whichever of their tasks needs it first
+type SharedProcesses = Arc<OnceLock<kondo_lib::Processes>>;
+
+fn spawn_clean_task(
+    proj: kondo_lib::Project,
+    processes: SharedProcesses,
+    pl: &mut ProjectList,
+    c: &mut Commands,
+) {
+    let ple = pl.0.iter_mut().find(|p| p.kproj.path == proj.path);
+
+    let Some(ple) = ple else {
+        return;
+    };
+    ple.status = ProjectListEntryStatus::Cleaning;
+    let artifact_paths: Vec<PathBuf> = ple.artifacts.iter().map(|(p, _)| p.clone()).collect();
+
+    let thread_pool = AsyncComputeTaskPool::get();
+    let task = thread_pool.spawn(async move {
+        let start = std::time::Instant::now();
+        let processes = processes.get_or_init(kondo_lib::Processes::snapshot);
+        let result = proj.clean_artifacts(
+            &artifact_paths,
+            &kondo_lib::CleanOptions::default(),
+            processes,
+        );
+        let elapsed = start.elapsed();
+
+        match &result {
+            Ok(()) => info!("Cleaned {:?} in {}ms", &proj, elapsed.as_millis()),
+            Err(in_use) => info!("Not cleaning {:?}, it {in_use}", &proj),
+        }
+
+        (proj, result)
+    });
+
+    c.spawn(CleanTask(task));
+}
 
 fn handle_clean_tasks(
     mut clean_tasks: Query<(Entity, &mut CleanTask)>,
     mut pl: ResMut<ProjectList>,
     mut sp: ResMut<SelectedProject>,
+    mut selection: ResMut<ProjectSelection>,
+    mut bulk: ResMut<BulkClean>,
     mut c: Commands,
 ) {
     for (e, mut task) in &mut clean_tasks {
-        if let Some(proj) = check_ready(&mut task.0) {
+        if let Some((proj, result)) = check_ready(&mut task.0) {
             {
-                let proj = pl.0.iter_mut().find(|p| p.kproj.path == proj.path);
-
-                if let Some(proj) = proj {
-                    proj.status = ProjectListEntryStatus::Cleaned;
-                    proj.size = 0;
+                // Done either way, the project may be gone from the list after a rescan
+                let pending = bulk.pending.remove(&proj.path);
+                let ple = pl.0.iter_mut().find(|p| p.kproj.path == proj.path);
+
+                if let Some(ple) = ple {
+                    match result {
+                        Ok(()) => {
+                            if pending {
+                                bulk.cleaned_bytes += ple.size;
+                            }
+                            ple.status = ProjectListEntryStatus::Cleaned;
+                            ple.size = 0;
+                        }
+                        Err(in_use) => ple.status = ProjectListEntryStatus::InUse(in_use),
+                    }
                 }
             }
 
+            selection.paths.remove(&proj.path);
+
             c.entity(e).despawn();
 
             if let Some(ple) = &sp.0
@@ -100,30 +186,69 @@ fn handle_clean_tasks(
     }
 }
 
+/// Messages from scan threads, tagged with the scan generation that produced them.
 enum BackgroundThreadMsg {
-    ScanningStarted(Vec<PathBuf>),
-    Ple(ProjectListEntry),
-    #[expect(unused)]
-    ScanningFinished,
+    ScanningStarted(u64, Vec<PathBuf>),
+    Ple(u64, ProjectListEntry),
+    ProjectDetails(u64, PathBuf, Option<SystemTime>, kondo_lib::ProjectSize),
+    ScanningFinished(u64),
 }
 
 struct BackgroundThreadCommunication {
     send: Sender<BackgroundThreadMsg>,
     recv: Receiver<BackgroundThreadMsg>,
+    /// Bumped whenever a scan is started or stopped. Scan threads bail out once it no longer
+    /// matches the generation they were started with, and their leftover messages are ignored.
+    scan_generation: Arc<AtomicU64>,
 }
 
 impl Default for BackgroundThreadCommunication {
     fn default() -> Self {
         let (send, recv) = std::sync::mpsc::channel();
 
-        Self { send, recv }
+        Self {
+            send,
+            recv,
+            scan_generation: Arc::new(AtomicU64::new(0)),
+        }
     }
 }
 
-const SCAN_OPTIONS: &kondo_lib::ScanOptions = &kondo_lib::ScanOptions {
-    follow_symlinks: false,
-    same_file_system: false,
-};
+#[derive(Resource, PartialEq, Eq)]
+enum ScanStatus {
+    Idle,
+    Scanning,
+    Finished,
+    Stopped,
+}
+
+/// Options for the next scan, changing them doesn't affect a scan that's already running
+#[derive(Resource, Default)]
+struct ScanSettings(kondo_lib::ScanOptions);
+
+#[derive(Component)]
+struct IncludeHiddenLabel;
+
+fn include_hidden_label(settings: &ScanSettings) -> &'static str {
+    if settings.0.include_hidden {
+        "Hidden Dirs: Included"
+    } else {
+        "Hidden Dirs: Skipped"
+    }
+}
+
+fn update_include_hidden_label(
+    mut q: Query<&mut Text, With<IncludeHiddenLabel>>,
+    settings: Res<ScanSettings>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for mut text in q.iter_mut() {
+        text.0 = include_hidden_label(&settings).to_owned();
+    }
+}
 
 fn setup(mut c: Commands) {
     c.spawn(Camera2d);
@@ -192,6 +317,20 @@ fn spawn_project_list(
         },
     ));
 
+    c.spawn((
+        TypeSummaryTag,
+        ChildOf(left_side),
+        Node {
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.),
+            padding: Val::Px(8.).into(),
+            width: Val::Percent(100.),
+            ..default()
+        },
+    ));
+
+    c.spawn((ChildOf(left_side), selection_toolbar()));
+
     c.spawn((
         ProjectListTag,
         ChildOf(left_side),
@@ -212,6 +351,17 @@ fn spawn_project_list(
         }
     });
 
+    c.spawn((
+        TreemapTag,
+        ChildOf(left_side),
+        Node {
+            display: Display::None,
+            width: Val::Percent(100.),
+            height: Val::Vh(60.),
+            ..default()
+        },
+    ));
+
     c.spawn((
         SelectedProjectTag,
         ChildOf(central_area_ui),
@@ -242,6 +392,7 @@ fn project_file_name(proj: &kondo_lib::Project) -> String {
 fn select_project_update(
     root: Query<Entity, With<SelectedProjectTag>>,
     sp: Res<SelectedProject>,
+    settings: Res<ScanSettings>,
     mut c: Commands,
 ) {
     let Ok(root) = root.single() else {
@@ -262,7 +413,10 @@ fn select_project_update(
 
     let display_name = project_file_name(&ple.kproj);
 
-    let mut dir_sizes = ple.kproj.size_dirs(SCAN_OPTIONS);
+    let mut dir_sizes = ple
+        .dir_sizes
+        .clone()
+        .unwrap_or_else(|| ple.kproj.size_dirs_of(ple.artifacts.clone(), &settings.0));
 
     dir_sizes
         .dirs
@@ -292,6 +446,13 @@ fn select_project_update(
                 Text::new(format!("{} project", ple.kproj.type_name())),
                 ThemedText,
             )),
+            Spawn((
+                Text::new(match ple.age_secs() {
+                    Some(age) => format!("Last modified {}", kondo_lib::print_elapsed(age)),
+                    None => "Last modified: calculating...".to_owned(),
+                }),
+                ThemedText,
+            )),
             Spawn((
                 Text::new(format!(
                     "{} Total Size",
@@ -332,6 +493,24 @@ fn select_project_update(
                     },
                 ))),
             )),
+            Spawn((Text::new("Artifact Directories"), ThemedText)),
+            Spawn((
+                Node {
+                    padding: UiRect::left(Val::Px(32.0)),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+                ThemedText,
+                Children::spawn(SpawnIter(dir_sizes.artifact_dirs.into_iter().map(
+                    |(path, size)| {
+                        (
+                            Text::new(format!("{path} {}", kondo_lib::pretty_size(size))),
+                            ThemedText,
+                        )
+                    },
+                ))),
+            )),
             Spawn((
                 button(
                     ButtonProps {
@@ -347,26 +526,7 @@ fn select_project_update(
                 ),
                 observe(
                     move |_: On<Activate>, mut pl: ResMut<ProjectList>, mut c: Commands| {
-                        let proj = proj.clone();
-
-                        let ple = pl.0.iter_mut().find(|p| p.kproj.path == proj.path);
-
-                        if let Some(ple) = ple {
-                            ple.status = ProjectListEntryStatus::Cleaning;
-                        }
-
-                        let thread_pool = AsyncComputeTaskPool::get();
-                        let task = thread_pool.spawn(async move {
-                            let start = std::time::Instant::now();
-                            proj.clean();
-                            let elapsed = start.elapsed();
-
-                            info!("Cleaned {:?} in {}ms", &proj, elapsed.as_millis());
-
-                            proj
-                        });
-
-                        c.spawn(CleanTask(task));
+                        spawn_clean_task(proj.clone(), SharedProcesses::default(), &mut pl, &mut c);
                     },
                 ),
             )),
@@ -386,43 +546,233 @@ fn font(size: f32) -> bevy::feathers::font_styles::InheritableFont {
 fn update_project_list_ui(
     q: Query<Entity, With<ProjectListTag>>,
     pl: Res<ProjectList>,
+    view: Res<ProjectListView>,
     mut c: Commands,
 ) {
-    if !pl.is_changed() {
+    if !pl.is_changed() && !view.is_changed() {
         return;
     }
 
     for pl_ui in q.iter() {
         c.entity(pl_ui).despawn_children().with_children(|c| {
-            for p in pl.iter() {
+            for p in pl.iter().filter(|p| view.matches(p)) {
                 c.spawn(build_project_list_entry(p.clone()));
             }
         });
     }
 
-    c.queue(SortProjectList::Size);
+    c.queue(view.sort);
+}
+
+const AGE_FILTERS: [(&str, u64); 4] = [
+    ("Any", 0),
+    ("1 month", 60 * 60 * 24 * 7 * 4),
+    ("6 months", 60 * 60 * 24 * 7 * 4 * 6),
+    ("1 year", 60 * 60 * 24 * 365),
+];
+
+const SIZE_FILTERS: [(&str, u64); 4] = [
+    ("Any", 0),
+    ("10MiB", 10 * 1024 * 1024),
+    ("100MiB", 100 * 1024 * 1024),
+    ("1GiB", 1024 * 1024 * 1024),
+];
+
+/// How the scanned projects are presented, changing this never triggers a rescan
+#[derive(Resource, Default)]
+struct ProjectListView {
+    sort: SortProjectList,
+    search: String,
+    type_filter: Option<&'static str>,
+    /// Index into [`AGE_FILTERS`]
+    age_filter: usize,
+    /// Index into [`SIZE_FILTERS`]
+    size_filter: usize,
+}
+
+impl ProjectListView {
+    fn matches(&self, ple: &ProjectListEntry) -> bool {
+        if let Some(type_filter) = self.type_filter
+            && ple.kproj.type_name() != type_filter
+        {
+            return false;
+        }
+
+        let min_age = AGE_FILTERS[self.age_filter].1;
+        if min_age > 0 && ple.age_secs().is_none_or(|age| age < min_age) {
+            return false;
+        }
+
+        if ple.size < SIZE_FILTERS[self.size_filter].1 {
+            return false;
+        }
+
+        if !self.search.is_empty() {
+            let search = self.search.to_lowercase();
+            let path = ple.kproj.path.to_string_lossy().to_lowercase();
+            let type_name = ple.kproj.type_name().to_lowercase();
+            if !path.contains(&search) && !type_name.contains(&search) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Component)]
+enum FilterLabel {
+    Search,
+    Type,
+    Age,
+    Size,
+}
+
+fn update_filter_labels(mut q: Query<(&mut Text, &FilterLabel)>, view: Res<ProjectListView>) {
+    if !view.is_changed() {
+        return;
+    }
+
+    for (mut text, label) in q.iter_mut() {
+        text.0 = match label {
+            FilterLabel::Search if view.search.is_empty() => "Search...".to_owned(),
+            FilterLabel::Search => view.search.clone(),
+            FilterLabel::Type => format!("Type: {}", view.type_filter.unwrap_or("All")),
+            FilterLabel::Age => format!("Older than: {}", AGE_FILTERS[view.age_filter].0),
+            FilterLabel::Size => format!("Larger than: {}", SIZE_FILTERS[view.size_filter].0),
+        };
+    }
+}
+
+fn search_box_input(mut input: On<FocusedInput<KeyboardInput>>, mut view: ResMut<ProjectListView>) {
+    let event = &input.event().input;
+    if event.state != ButtonState::Pressed {
+        return;
+    }
+
+    match event.key_code {
+        KeyCode::Backspace => {
+            view.search.pop();
+        }
+        KeyCode::Escape => view.search.clear(),
+        // Leave these for tab navigation
+        KeyCode::Tab | KeyCode::Enter => return,
+        _ => match &event.text {
+            Some(text) if text.chars().all(|c| !c.is_control()) => view.search.push_str(text),
+            _ => return,
+        },
+    }
+
+    input.propagate(false);
+}
+
+fn cycle_type_filter(_: On<Activate>, pl: Res<ProjectList>, mut view: ResMut<ProjectListView>) {
+    let mut types: Vec<&'static str> = pl.iter().map(|ple| ple.kproj.type_name()).collect();
+    types.sort_unstable();
+    types.dedup();
+
+    view.type_filter = match view.type_filter {
+        None => types.first().copied(),
+        Some(current) => types
+            .iter()
+            .position(|t| *t == current)
+            .and_then(|i| types.get(i + 1))
+            .copied(),
+    };
+}
+
+fn filter_bar() -> impl Bundle {
+    (
+        Node {
+            display: Display::Flex,
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Start,
+            column_gap: Val::Px(8.0),
+            padding: UiRect::horizontal(Val::Px(8.)),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Node {
+                    width: Val::Px(240.),
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(4.)),
+                    border_radius: BorderRadius::all(Val::Px(4.0)),
+                    ..default()
+                },
+                TabIndex(0),
+                ThemeBackgroundColor(tokens::SLIDER_BG),
+                font(14.),
+                Children::spawn_one((Text::new("Search..."), ThemedText, FilterLabel::Search)),
+                observe(search_box_input),
+            )),
+            Spawn((
+                button(
+                    ButtonProps::default(),
+                    (),
+                    Spawn((Text::new("Type: All"), ThemedText, FilterLabel::Type)),
+                ),
+                observe(cycle_type_filter),
+            )),
+            Spawn((
+                button(
+                    ButtonProps::default(),
+                    (),
+                    Spawn((Text::new("Older than: Any"), ThemedText, FilterLabel::Age)),
+                ),
+                observe(|_: On<Activate>, mut view: ResMut<ProjectListView>| {
+                    view.age_filter = (view.age_filter + 1) % AGE_FILTERS.len();
+                }),
+            )),
+            Spawn((
+                button(
+                    ButtonProps::default(),
+                    (),
+                    Spawn((Text::new("Larger than: Any"), ThemedText, FilterLabel::Size)),
+                ),
+                observe(|_: On<Activate>, mut view: ResMut<ProjectListView>| {
+                    view.size_filter = (view.size_filter + 1) % SIZE_FILTERS.len();
+                }),
+            )),
+        )),
+    )
 }
 
 #[derive(Component)]
 struct RootUITag;
 
-fn discover_projects(dirs: &[std::path::PathBuf]) -> impl Iterator<Item = kondo_lib::Project> {
-    dirs.iter()
-        .flatten()
-        .flat_map(|dir| kondo_lib::scan(&dir, SCAN_OPTIONS))
-        .filter_map(Result::ok)
+fn discover_projects<'a>(
+    dirs: &[std::path::PathBuf],
+    options: &'a kondo_lib::ScanOptions,
+) -> impl Iterator<Item = kondo_lib::Project> + 'a {
+    kondo_lib::scan_roots(dirs, options).filter_map(Result::ok)
 }
 
 fn process_new_projects(
     tc: NonSend<BackgroundThreadCommunication>,
     mut pl: ResMut<ProjectList>,
+    mut sp: ResMut<SelectedProject>,
+    mut status: ResMut<ScanStatus>,
+    mut scan_roots: ResMut<ScanRoots>,
     sdl: Query<Entity, With<ScanningDirsListTag>>,
     mut c: Commands,
 ) {
     while let Ok(msg) = tc.recv.try_recv() {
+        let current_generation = tc.scan_generation.load(Ordering::SeqCst);
+
         match msg {
-            BackgroundThreadMsg::ScanningStarted(dirs) => {
+            BackgroundThreadMsg::ScanningStarted(generation, _)
+            | BackgroundThreadMsg::Ple(generation, _)
+            | BackgroundThreadMsg::ProjectDetails(generation, _, _, _)
+            | BackgroundThreadMsg::ScanningFinished(generation)
+                if generation != current_generation =>
+            {
+                // Results from a scan that has since been stopped or replaced
+            }
+            BackgroundThreadMsg::ScanningStarted(_, dirs) => {
                 pl.clear();
+                *status = ScanStatus::Scanning;
+                scan_roots.0.clone_from(&dirs);
 
                 if let Some(sdl) = sdl.iter().next() {
                     c.entity(sdl).despawn_children();
@@ -431,24 +781,84 @@ fn process_new_projects(
                     }
                 }
             }
-            BackgroundThreadMsg::Ple(ple) => {
+            BackgroundThreadMsg::Ple(_, ple) => {
                 pl.push(ple);
             }
-            BackgroundThreadMsg::ScanningFinished => {}
+            BackgroundThreadMsg::ProjectDetails(_, path, last_modified, dir_sizes) => {
+                if let Some(ple) = pl.iter_mut().find(|p| p.kproj.path == path) {
+                    ple.last_modified = last_modified;
+                    ple.dir_sizes = Some(dir_sizes.clone());
+                }
+                // Only touch the selection when it matches, so the detail pane isn't rebuilt
+                if sp.0.as_ref().is_some_and(|ple| ple.kproj.path == path)
+                    && let Some(ple) = &mut sp.0
+                {
+                    ple.last_modified = last_modified;
+                    ple.dir_sizes = Some(dir_sizes);
+                }
+            }
+            BackgroundThreadMsg::ScanningFinished(_) => {
+                *status = ScanStatus::Finished;
+            }
+        }
+    }
+}
+
+fn update_scan_status_ui(
+    mut q: Query<&mut Text, With<ScanStatusTag>>,
+    status: Res<ScanStatus>,
+    pl: Res<ProjectList>,
+) {
+    if !status.is_changed() && !pl.is_changed() {
+        return;
+    }
+
+    let pending_details = pl.iter().filter(|ple| ple.dir_sizes.is_none()).count();
+    let details = if pending_details > 0 {
+        format!(", calculating details of {pending_details}")
+    } else {
+        String::new()
+    };
+
+    let text = match *status {
+        ScanStatus::Idle => String::new(),
+        ScanStatus::Scanning => format!("Scanning... {} projects found{details}", pl.len()),
+        ScanStatus::Finished => {
+            format!("Scanning finished, {} projects found{details}", pl.len())
         }
+        ScanStatus::Stopped => format!("Scan stopped, {} projects found", pl.len()),
+    };
+
+    for mut t in q.iter_mut() {
+        t.0.clone_from(&text);
     }
 }
 
-fn select_directory(_: On<Activate>, tc: NonSend<BackgroundThreadCommunication>) {
+fn select_directory(
+    _: On<Activate>,
+    tc: NonSend<BackgroundThreadCommunication>,
+    settings: Res<ScanSettings>,
+) {
     let main_thread_send = tc.send.clone();
+    let scan_generation = tc.scan_generation.clone();
+    let options = settings.0.clone();
 
     std::thread::spawn(move || {
         let Some(dirs) = rfd::FileDialog::new().pick_folders() else {
             return;
         };
+        // Picking a folder along with one inside it would list its projects twice
+        let dirs = kondo_lib::normalise_roots(&dirs);
+
+        // Starting a new scan invalidates any scan that's still running
+        let generation = scan_generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let is_current = move || scan_generation.load(Ordering::SeqCst) == generation;
 
         if main_thread_send
-            .send(BackgroundThreadMsg::ScanningStarted(dirs.clone()))
+            .send(BackgroundThreadMsg::ScanningStarted(
+                generation,
+                dirs.clone(),
+            ))
             .is_err()
         {
             return;
@@ -456,32 +866,90 @@ fn select_directory(_: On<Activate>, tc: NonSend<BackgroundThreadCommunication>)
 
         let (raw_proj_send, raw_proj_recv) = std::sync::mpsc::channel();
 
+        let discover_is_current = is_current.clone();
+        let discover_options = options.clone();
         std::thread::spawn(move || {
             info!("Searching {:?}", &dirs);
-            for raw_proj in discover_projects(&dirs) {
-                if raw_proj_send.send(raw_proj).is_err() {
+            for raw_proj in discover_projects(&dirs, &discover_options) {
+                if !discover_is_current() || raw_proj_send.send(raw_proj).is_err() {
+                    return;
+                }
+            }
+        });
+
+        // Walking a whole project for its last modified time and directory sizes is much slower
+        // than sizing its artifacts, so it's done separately to let projects show up sooner.
+        let (age_send, age_recv) =
+            std::sync::mpsc::channel::<(kondo_lib::Project, Vec<(PathBuf, u64)>)>();
+
+        let age_is_current = is_current.clone();
+        let age_main_thread_send = main_thread_send.clone();
+        let age_options = options.clone();
+        std::thread::spawn(move || {
+            while let Ok((proj, artifacts)) = age_recv.recv() {
+                if !age_is_current() {
+                    return;
+                }
+
+                let last_modified = proj.last_modified(&age_options).ok();
+                let dir_sizes = proj.size_dirs_of(artifacts, &age_options);
+
+                if age_main_thread_send
+                    .send(BackgroundThreadMsg::ProjectDetails(
+                        generation,
+                        proj.path,
+                        last_modified,
+                        dir_sizes,
+                    ))
+                    .is_err()
+                {
                     return;
                 }
             }
         });
 
         while let Ok(raw_proj) = raw_proj_recv.recv() {
+            if !is_current() {
+                return;
+            }
+
+            let artifacts = raw_proj.artifact_sizes(&options);
             let proj_entry = ProjectListEntry {
-                size: raw_proj.size(SCAN_OPTIONS),
-                kproj: raw_proj,
+                size: artifacts.iter().map(|(_, size)| size).sum(),
+                artifacts: artifacts.clone(),
+                last_modified: None,
+                dir_sizes: None,
+                kproj: raw_proj.clone(),
                 status: ProjectListEntryStatus::Uncleaned,
             };
 
             if main_thread_send
-                .send(BackgroundThreadMsg::Ple(proj_entry))
+                .send(BackgroundThreadMsg::Ple(generation, proj_entry))
                 .is_err()
+                || age_send.send((raw_proj, artifacts)).is_err()
             {
                 return;
             }
         }
+
+        // The walk is done, project details keep coming in afterwards
+        let _ = main_thread_send.send(BackgroundThreadMsg::ScanningFinished(generation));
     });
 }
 
+fn stop_scan(
+    _: On<Activate>,
+    tc: NonSend<BackgroundThreadCommunication>,
+    mut status: ResMut<ScanStatus>,
+) {
+    if *status != ScanStatus::Scanning {
+        return;
+    }
+
+    tc.scan_generation.fetch_add(1, Ordering::SeqCst);
+    *status = ScanStatus::Stopped;
+}
+
 fn spawn_root(_: &mut Commands) -> impl Bundle {
     (
         RootUITag,
@@ -497,55 +965,141 @@ fn spawn_root(_: &mut Commands) -> impl Bundle {
         },
         TabGroup::default(),
         ThemeBackgroundColor(tokens::WINDOW_BG),
-        Children::spawn_one((
-            Node {
-                display: Display::Flex,
-                flex_direction: FlexDirection::Row,
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Start,
-                column_gap: Val::Px(8.0),
-                padding: UiRect::all(Val::Px(8.)),
-                ..default()
-            },
-            Children::spawn((
-                Spawn((
-                    button(
-                        ButtonProps::default(),
-                        (),
-                        Spawn((Text::new("Sort by Name"), ThemedText)),
-                    ),
-                    observe(|_: On<Activate>, mut c: Commands| c.queue(SortProjectList::Name)),
-                )),
-                Spawn((
-                    button(
-                        ButtonProps::default(),
-                        (),
-                        Spawn((Text::new("Sort by Size"), ThemedText)),
-                    ),
-                    observe(|_: On<Activate>, mut c: Commands| c.queue(SortProjectList::Size)),
-                )),
-                Spawn((
-                    button(
-                        ButtonProps {
-                            variant: ButtonVariant::Primary,
-                            ..default()
-                        },
-                        (),
-                        Spawn((Text::new("Select Directory"), ThemedText)),
-                    ),
-                    observe(select_directory),
+        Children::spawn((
+            Spawn((
+                Node {
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Start,
+                    column_gap: Val::Px(8.0),
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                Children::spawn((
+                    Spawn(sort_button("Sort by Name", SortProjectList::Name)),
+                    Spawn(sort_button("Sort by Size", SortProjectList::Size)),
+                    Spawn(sort_button("Sort by Type", SortProjectList::Type)),
+                    Spawn(sort_button("Sort by Age", SortProjectList::LastModified)),
+                    Spawn(sort_button("Sort by Path", SortProjectList::Path)),
+                    Spawn((
+                        button(
+                            ButtonProps::default(),
+                            (),
+                            Spawn((Text::new("List / Treemap"), ThemedText)),
+                        ),
+                        observe(|_: On<Activate>, mut pane: ResMut<LeftPane>| {
+                            *pane = match *pane {
+                                LeftPane::List => LeftPane::Treemap,
+                                LeftPane::Treemap => LeftPane::List,
+                            };
+                        }),
+                    )),
+                    Spawn((
+                        button(
+                            ButtonProps {
+                                variant: ButtonVariant::Primary,
+                                ..default()
+                            },
+                            (),
+                            Spawn((Text::new("Select Directory"), ThemedText)),
+                        ),
+                        observe(select_directory),
+                    )),
+                    Spawn((
+                        button(
+                            ButtonProps::default(),
+                            (),
+                            Spawn((
+                                Text::new(include_hidden_label(&ScanSettings::default())),
+                                ThemedText,
+                                IncludeHiddenLabel,
+                            )),
+                        ),
+                        observe(|_: On<Activate>, mut settings: ResMut<ScanSettings>| {
+                            settings.0.include_hidden = !settings.0.include_hidden;
+                        }),
+                    )),
+                    Spawn((
+                        button(
+                            ButtonProps::default(),
+                            (),
+                            Spawn((Text::new("Stop Scan"), ThemedText)),
+                        ),
+                        observe(stop_scan),
+                    )),
+                    Spawn((ScanStatusTag, Text::new(""), ThemedText)),
                 )),
             )),
+            Spawn(filter_bar()),
         )),
     )
 }
 
+fn sort_button(label: &'static str, sort: SortProjectList) -> impl Bundle {
+    (
+        button(
+            ButtonProps::default(),
+            (),
+            Spawn((Text::new(label), ThemedText)),
+        ),
+        observe(move |_: On<Activate>, mut view: ResMut<ProjectListView>| {
+            view.sort = sort;
+        }),
+    )
+}
+
 #[derive(Component)]
 struct LeftSideTag;
 
 #[derive(Component)]
 struct ScanningDirsListTag;
 
+#[derive(Component)]
+struct ScanStatusTag;
+
+#[derive(Component)]
+struct TypeSummaryTag;
+
+/// Lists reclaimable bytes per project type, largest first
+fn update_type_summary(
+    q: Query<Entity, With<TypeSummaryTag>>,
+    pl: Res<ProjectList>,
+    mut c: Commands,
+) {
+    if !pl.is_changed() {
+        return;
+    }
+
+    let mut totals: HashMap<&'static str, (u64, usize)> = HashMap::new();
+    for ple in pl
+        .iter()
+        .filter(|ple| ple.status != ProjectListEntryStatus::Cleaned)
+    {
+        let (bytes, count) = totals.entry(ple.kproj.type_name()).or_default();
+        *bytes += ple.size;
+        *count += 1;
+    }
+
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_unstable_by_key(|(type_name, (bytes, _))| (std::cmp::Reverse(*bytes), *type_name));
+
+    for summary in q.iter() {
+        c.entity(summary).despawn_children().with_children(|c| {
+            for (type_name, (bytes, count)) in totals.iter() {
+                c.spawn((
+                    Text::new(format!(
+                        "{type_name}: {} across {count} project{}",
+                        kondo_lib::pretty_size(*bytes),
+                        if *count == 1 { "" } else { "s" }
+                    )),
+                    ThemedText,
+                ));
+            }
+        });
+    }
+}
+
 #[derive(Component)]
 struct ProjectListTag;
 
@@ -558,15 +1112,36 @@ struct CentralUIAreaTag;
 #[derive(Component, Clone)]
 struct ProjectListEntry {
     kproj: kondo_lib::Project,
+    /// Found with the options of the scan that listed the project, they're what gets cleaned
+    artifacts: Vec<(PathBuf, u64)>,
     size: u64,
+    last_modified: Option<std::time::SystemTime>,
+    /// Filled in by the background pass after the project is first listed
+    dir_sizes: Option<kondo_lib::ProjectSize>,
     status: ProjectListEntryStatus,
 }
 
-#[derive(Clone)]
+impl ProjectListEntry {
+    fn age_secs(&self) -> Option<u64> {
+        self.last_modified
+            .and_then(|lm| lm.elapsed().ok())
+            .map(|elapsed| elapsed.as_secs())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
 enum ProjectListEntryStatus {
     Uncleaned,
     Cleaning,
     Cleaned,
+    /// Cleaning was skipped, it can be tried again once the build or process is done
+    InUse(kondo_lib::InUse),
+}
+
+impl ProjectListEntryStatus {
+    fn is_cleanable(&self) -> bool {
+        matches!(self, Self::Uncleaned | Self::InUse(_))
+    }
 }
 
 fn build_project_list_entry(ple: ProjectListEntry) -> impl Bundle {
@@ -577,34 +1152,43 @@ fn build_project_list_entry(ple: ProjectListEntry) -> impl Bundle {
         .map(|n| n.to_string_lossy())
         .unwrap_or_else(|| proj.name());
     let text = format!(
-        "{} ({}) {} {}",
+        "{} ({}) {} {} {}",
         display_name,
         proj.type_name(),
         kondo_lib::pretty_size(ple.size),
-        match ple.status {
-            ProjectListEntryStatus::Uncleaned => "",
-            ProjectListEntryStatus::Cleaning => "Cleaning",
-            ProjectListEntryStatus::Cleaned => "Cleaned",
+        ple.age_secs()
+            .map(kondo_lib::print_elapsed)
+            .unwrap_or_default(),
+        match &ple.status {
+            ProjectListEntryStatus::Uncleaned => String::new(),
+            ProjectListEntryStatus::Cleaning => "Cleaning".to_owned(),
+            ProjectListEntryStatus::Cleaned => "Cleaned".to_owned(),
+            ProjectListEntryStatus::InUse(in_use) => format!("Not cleaned, it {in_use}"),
         }
     );
 
     (
         Node {
             // padding: UiRect::vertical(Val::Px(8.0)),
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(8.0),
             ..default()
         },
         ple.clone(),
-        Children::spawn_one((
-            button_left(
-                ButtonProps::default(),
-                ple,
-                Spawn((
-                    Text::new(text),
-                    TextLayout::new_with_linebreak(LineBreak::WordOrCharacter),
-                    ThemedText,
-                )),
-            ),
-            observe(project_list_entry_clicked),
+        Children::spawn((
+            Spawn((checkbox(ple.clone(), ()), observe(project_checkbox_changed))),
+            Spawn((
+                button_left(
+                    ButtonProps::default(),
+                    ple,
+                    Spawn((
+                        Text::new(text),
+                        TextLayout::new_with_linebreak(LineBreak::WordOrCharacter),
+                        ThemedText,
+                    )),
+                ),
+                observe(project_list_entry_clicked),
+            )),
         )),
     )
 }
@@ -659,9 +1243,342 @@ fn project_list_entry_clicked(
     }
 }
 
+#[derive(Resource, Default)]
+struct ProjectSelection {
+    paths: HashSet<PathBuf>,
+    /// Last project toggled without shift held, shift-clicking selects from here
+    anchor: Option<PathBuf>,
+}
+
+impl ProjectSelection {
+    fn select_where(&mut self, pl: &ProjectList, f: impl Fn(&ProjectListEntry) -> bool) {
+        self.paths.extend(
+            pl.iter()
+                .filter(|ple| ple.status.is_cleanable() && f(ple))
+                .map(|ple| ple.kproj.path.clone()),
+        );
+    }
+}
+
+fn project_checkbox_changed(
+    change: On<ValueChange<bool>>,
+    ple: Query<&ProjectListEntry>,
+    list: Query<&Children, With<ProjectListTag>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut selection: ResMut<ProjectSelection>,
+) {
+    let Ok(clicked) = ple.get(change.source) else {
+        return;
+    };
+    let clicked = clicked.kproj.path.clone();
+
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let mut range = vec![clicked.clone()];
+
+    if shift && let Some(anchor) = &selection.anchor {
+        // Rows in the order they're currently displayed
+        let rows: Vec<&PathBuf> = list
+            .iter()
+            .flat_map(|children| children.iter())
+            .filter_map(|row| ple.get(row).ok())
+            .map(|ple| &ple.kproj.path)
+            .collect();
+
+        let start = rows.iter().position(|p| *p == anchor);
+        let end = rows.iter().position(|p| **p == clicked);
+
+        if let (Some(start), Some(end)) = (start, end) {
+            range = rows[start.min(end)..=start.max(end)]
+                .iter()
+                .map(|p| (*p).clone())
+                .collect();
+        }
+    }
+
+    for path in range {
+        if change.value {
+            selection.paths.insert(path);
+        } else {
+            selection.paths.remove(&path);
+        }
+    }
+
+    if !shift {
+        selection.anchor = Some(clicked);
+    }
+}
+
+fn sync_selection_checkboxes(
+    selection: Res<ProjectSelection>,
+    pl: Res<ProjectList>,
+    checkboxes: Query<(Entity, &ProjectListEntry, Has<Checked>), With<Checkbox>>,
+    mut c: Commands,
+) {
+    if !selection.is_changed() && !pl.is_changed() {
+        return;
+    }
+
+    for (e, ple, checked) in checkboxes.iter() {
+        let selected = selection.paths.contains(&ple.kproj.path);
+        if selected && !checked {
+            c.entity(e).insert(Checked);
+        } else if !selected && checked {
+            c.entity(e).remove::<Checked>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct SelectTypeButtonsTag;
+
+#[derive(Component)]
+struct SelectionSummaryTag;
+
+#[derive(Component)]
+struct BulkCleanProgressTag;
+
+const SELECT_OLDER_THAN: [(&str, u64); 3] = [
+    ("1 month", 60 * 60 * 24 * 7 * 4),
+    ("6 months", 60 * 60 * 24 * 7 * 4 * 6),
+    ("1 year", 60 * 60 * 24 * 365),
+];
+
+fn selection_toolbar() -> impl Bundle {
+    (
+        Node {
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(8.),
+            padding: Val::Px(8.).into(),
+            width: Val::Percent(100.),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.),
+                    ..default()
+                },
+                Children::spawn((
+                    Spawn((Text::new("Select older than"), ThemedText)),
+                    SpawnIter(SELECT_OLDER_THAN.into_iter().map(|(label, secs)| {
+                        (
+                            button(
+                                ButtonProps::default(),
+                                (),
+                                Spawn((Text::new(label), ThemedText)),
+                            ),
+                            observe(
+                                move |_: On<Activate>,
+                                      pl: Res<ProjectList>,
+                                      mut selection: ResMut<ProjectSelection>| {
+                                    selection.select_where(&pl, |ple| {
+                                        ple.age_secs().is_some_and(|age| age >= secs)
+                                    });
+                                },
+                            ),
+                        )
+                    })),
+                    Spawn((
+                        button(
+                            ButtonProps::default(),
+                            (),
+                            Spawn((Text::new("Select None"), ThemedText)),
+                        ),
+                        observe(|_: On<Activate>, mut selection: ResMut<ProjectSelection>| {
+                            selection.paths.clear();
+                            selection.anchor = None;
+                        }),
+                    )),
+                )),
+            )),
+            Spawn((
+                SelectTypeButtonsTag,
+                Node {
+                    align_items: AlignItems::Center,
+                    flex_wrap: FlexWrap::Wrap,
+                    column_gap: Val::Px(8.),
+                    row_gap: Val::Px(8.),
+                    ..default()
+                },
+            )),
+            Spawn((
+                Node {
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(8.),
+                    ..default()
+                },
+                Children::spawn((
+                    Spawn((
+                        button(
+                            ButtonProps {
+                                variant: ButtonVariant::Primary,
+                                ..default()
+                            },
+                            (),
+                            Spawn((Text::new("Clean Selected"), ThemedText)),
+                        ),
+                        observe(clean_selected),
+                    )),
+                    Spawn((SelectionSummaryTag, Text::new(""), ThemedText)),
+                    Spawn((BulkCleanProgressTag, Text::new(""), ThemedText)),
+                )),
+            )),
+        )),
+    )
+}
+
+fn update_select_type_buttons(
+    q: Query<Entity, With<SelectTypeButtonsTag>>,
+    pl: Res<ProjectList>,
+    mut shown_types: Local<Vec<&'static str>>,
+    mut c: Commands,
+) {
+    if !pl.is_changed() {
+        return;
+    }
+
+    let mut types: Vec<&'static str> = pl.iter().map(|ple| ple.kproj.type_name()).collect();
+    types.sort_unstable();
+    types.dedup();
+
+    if *shown_types == types {
+        return;
+    }
+
+    for container in q.iter() {
+        c.entity(container).despawn_children().with_children(|c| {
+            if !types.is_empty() {
+                c.spawn((Text::new("Select type"), ThemedText));
+            }
+            for type_name in types.iter().copied() {
+                c.spawn((
+                    button(
+                        ButtonProps::default(),
+                        (),
+                        Spawn((Text::new(type_name), ThemedText)),
+                    ),
+                    observe(
+                        move |_: On<Activate>,
+                              pl: Res<ProjectList>,
+                              mut selection: ResMut<ProjectSelection>| {
+                            selection.select_where(&pl, |ple| ple.kproj.type_name() == type_name);
+                        },
+                    ),
+                ));
+            }
+        });
+    }
+
+    *shown_types = types;
+}
+
+fn update_selection_summary(
+    mut q: Query<&mut Text, With<SelectionSummaryTag>>,
+    selection: Res<ProjectSelection>,
+    pl: Res<ProjectList>,
+) {
+    if !selection.is_changed() && !pl.is_changed() {
+        return;
+    }
+
+    let (count, bytes) = pl
+        .iter()
+        .filter(|ple| selection.paths.contains(&ple.kproj.path))
+        .filter(|ple| ple.status.is_cleanable())
+        .fold((0, 0), |(count, bytes), ple| (count + 1, bytes + ple.size));
+
+    let text = format!(
+        "{count} selected, {} reclaimable",
+        kondo_lib::pretty_size(bytes)
+    );
+
+    for mut t in q.iter_mut() {
+        t.0.clone_from(&text);
+    }
+}
+
+/// Progress of the most recent "Clean Selected" action
+#[derive(Resource, Default)]
+struct BulkClean {
+    pending: HashSet<PathBuf>,
+    projects: usize,
+    bytes: u64,
+    cleaned_bytes: u64,
+}
+
+fn clean_selected(
+    _: On<Activate>,
+    mut pl: ResMut<ProjectList>,
+    selection: Res<ProjectSelection>,
+    mut bulk: ResMut<BulkClean>,
+    mut c: Commands,
+) {
+    let projects: Vec<(kondo_lib::Project, u64)> = pl
+        .iter()
+        .filter(|ple| selection.paths.contains(&ple.kproj.path))
+        .filter(|ple| ple.status.is_cleanable())
+        .map(|ple| (ple.kproj.clone(), ple.size))
+        .collect();
+
+    if projects.is_empty() {
+        return;
+    }
+
+    // Start a fresh tally unless a previous bulk clean is still running
+    if bulk.pending.is_empty() {
+        *bulk = BulkClean::default();
+    }
+
+    let processes = SharedProcesses::default();
+    for (proj, size) in projects {
+        bulk.pending.insert(proj.path.clone());
+        bulk.projects += 1;
+        bulk.bytes += size;
+        spawn_clean_task(proj, processes.clone(), &mut pl, &mut c);
+    }
+}
+
+fn update_bulk_clean_progress(
+    mut q: Query<&mut Text, With<BulkCleanProgressTag>>,
+    bulk: Res<BulkClean>,
+) {
+    if !bulk.is_changed() {
+        return;
+    }
+
+    let text = if bulk.projects == 0 {
+        String::new()
+    } else {
+        let verb = if bulk.pending.is_empty() {
+            "Cleaned"
+        } else {
+            "Cleaning"
+        };
+        format!(
+            "{verb} {}/{} projects ({} / {})",
+            bulk.projects - bulk.pending.len(),
+            bulk.projects,
+            kondo_lib::pretty_size(bulk.cleaned_bytes),
+            kondo_lib::pretty_size(bulk.bytes)
+        )
+    };
+
+    for mut t in q.iter_mut() {
+        t.0.clone_from(&text);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
 enum SortProjectList {
     Name,
+    #[default]
     Size,
+    Type,
+    /// Least recently modified first
+    LastModified,
+    Path,
 }
 
 impl Command for SortProjectList {
@@ -686,6 +1603,18 @@ fn sort_projectlist(
                     std::cmp::Reverse(ple.size)
                 });
             }
+            SortProjectList::Type => {
+                pl.sort_by_key(|k| {
+                    let ple = ple.get(*k).unwrap();
+                    (ple.kproj.type_name(), std::cmp::Reverse(ple.size))
+                });
+            }
+            SortProjectList::LastModified => {
+                pl.sort_by_key(|k| ple.get(*k).unwrap().last_modified);
+            }
+            SortProjectList::Path => {
+                pl.sort_by_key(|k| ple.get(*k).unwrap().kproj.path.clone());
+            }
         }
     }
 }
@@ -776,3 +1705,311 @@ fn on_scroll_handler(
         scroll.propagate(false);
     }
 }
+
+/// Directories picked for the current scan, the top level of the treemap
+#[derive(Resource, Default)]
+struct ScanRoots(Vec<PathBuf>);
+
+#[derive(Resource, Default, PartialEq, Eq)]
+enum LeftPane {
+    #[default]
+    List,
+    Treemap,
+}
+
+#[derive(Component)]
+struct TreemapTag;
+
+fn update_left_pane_visibility(
+    pane: Res<LeftPane>,
+    mut list: Query<&mut Node, (With<ProjectListTag>, Without<TreemapTag>)>,
+    mut treemap: Query<&mut Node, (With<TreemapTag>, Without<ProjectListTag>)>,
+) {
+    if !pane.is_changed() {
+        return;
+    }
+
+    let (list_display, treemap_display) = match *pane {
+        LeftPane::List => (Display::Flex, Display::None),
+        LeftPane::Treemap => (Display::None, Display::Flex),
+    };
+
+    for mut node in list.iter_mut() {
+        node.display = list_display;
+    }
+    for mut node in treemap.iter_mut() {
+        node.display = treemap_display;
+    }
+}
+
+struct TreemapItem {
+    label: String,
+    bytes: u64,
+    /// Set on project and artifact tiles so clicking them selects the project
+    project: Option<PathBuf>,
+    children: Vec<TreemapItem>,
+}
+
+impl TreemapItem {
+    fn group(label: String, children: Vec<TreemapItem>) -> Self {
+        Self {
+            label,
+            bytes: children.iter().map(|c| c.bytes).sum(),
+            project: None,
+            children,
+        }
+    }
+}
+
+/// Projects grouped by the directory containing them
+type DirGroup<'a> = (PathBuf, Vec<&'a ProjectListEntry>);
+
+/// Groups projects by scan root, then the directory they're in, then the project itself, then
+/// its artifact directories.
+fn build_treemap_items(
+    pl: &ProjectList,
+    view: &ProjectListView,
+    roots: &[PathBuf],
+) -> Vec<TreemapItem> {
+    let mut grouped: Vec<(PathBuf, Vec<DirGroup>)> = roots
+        .iter()
+        .map(|root| (root.clone(), Vec::new()))
+        .collect();
+
+    for ple in pl.iter().filter(|ple| ple.size > 0 && view.matches(ple)) {
+        let path = &ple.kproj.path;
+        let parent = path.parent().unwrap_or(path).to_path_buf();
+        let root_index = match grouped.iter().position(|(root, _)| path.starts_with(root)) {
+            Some(i) => i,
+            None => {
+                grouped.push((parent.clone(), Vec::new()));
+                grouped.len() - 1
+            }
+        };
+        let dirs = &mut grouped[root_index].1;
+        match dirs.iter_mut().find(|(dir, _)| *dir == parent) {
+            Some((_, projects)) => projects.push(ple),
+            None => dirs.push((parent, vec![ple])),
+        }
+    }
+
+    grouped
+        .into_iter()
+        .map(|(root, dirs)| {
+            let dirs = dirs
+                .into_iter()
+                .map(|(dir, projects)| {
+                    let label = match dir.strip_prefix(&root) {
+                        Ok(rel) if rel.as_os_str().is_empty() => ".".to_owned(),
+                        Ok(rel) => rel.to_string_lossy().into_owned(),
+                        Err(_) => dir.to_string_lossy().into_owned(),
+                    };
+                    let projects = projects.into_iter().map(treemap_project_item).collect();
+                    TreemapItem::group(label, projects)
+                })
+                .collect();
+            TreemapItem::group(root.to_string_lossy().into_owned(), dirs)
+        })
+        .filter(|item| item.bytes > 0)
+        .collect()
+}
+
+fn treemap_project_item(ple: &ProjectListEntry) -> TreemapItem {
+    let project = Some(ple.kproj.path.clone());
+
+    let children = ple
+        .dir_sizes
+        .iter()
+        .flat_map(|ds| ds.artifact_dirs.iter())
+        .filter(|(_, size)| *size > 0)
+        .map(|(name, size)| TreemapItem {
+            label: name.clone(),
+            bytes: *size,
+            project: project.clone(),
+            children: Vec::new(),
+        })
+        .collect();
+
+    TreemapItem {
+        label: format!(
+            "{} ({})",
+            project_file_name(&ple.kproj),
+            ple.kproj.type_name()
+        ),
+        bytes: ple.size,
+        project,
+        children,
+    }
+}
+
+/// Squarified treemap layout of `sizes` (largest first) within a `width` x `height` area.
+/// Returns the `(x, y, width, height)` of each size in the same units as the area.
+fn squarify(sizes: &[u64], width: f32, height: f32) -> Vec<(f32, f32, f32, f32)> {
+    let total: u64 = sizes.iter().sum();
+    if total == 0 {
+        return vec![(0., 0., 0., 0.); sizes.len()];
+    }
+
+    let scale = width * height / total as f32;
+    let areas: Vec<f32> = sizes.iter().map(|s| *s as f32 * scale).collect();
+
+    // Worst aspect ratio of a row of areas laid along a side of the given length
+    fn worst(row: &[f32], side: f32) -> f32 {
+        let sum: f32 = row.iter().sum();
+        let max = row.iter().copied().fold(f32::MIN, f32::max);
+        let min = row.iter().copied().fold(f32::MAX, f32::min);
+        f32::max(
+            side * side * max / (sum * sum),
+            sum * sum / (side * side * min),
+        )
+    }
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let (mut x, mut y, mut w, mut h) = (0., 0., width, height);
+    let mut start = 0;
+
+    while start < areas.len() {
+        let side = w.min(h);
+        let mut end = start + 1;
+        let mut best = worst(&areas[start..end], side);
+        while end < areas.len() {
+            let next = worst(&areas[start..end + 1], side);
+            if next > best {
+                break;
+            }
+            best = next;
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_sum: f32 = row.iter().sum();
+
+        if w >= h {
+            let col_w = row_sum / h;
+            let mut cy = y;
+            for area in row {
+                let rh = area / col_w;
+                rects.push((x, cy, col_w, rh));
+                cy += rh;
+            }
+            x += col_w;
+            w -= col_w;
+        } else {
+            let row_h = row_sum / w;
+            let mut cx = x;
+            for area in row {
+                let rw = area / row_h;
+                rects.push((cx, y, rw, row_h));
+                cx += rw;
+            }
+            y += row_h;
+            h -= row_h;
+        }
+
+        start = end;
+    }
+
+    rects
+}
+
+const TREEMAP_COLOURS: [Color; 4] = [
+    Color::srgb(0.16, 0.17, 0.21),
+    Color::srgb(0.20, 0.25, 0.32),
+    Color::srgb(0.22, 0.34, 0.45),
+    Color::srgb(0.27, 0.45, 0.38),
+];
+
+/// Tiles smaller than this (in the treemap's nominal pixel space) aren't drawn
+const TREEMAP_MIN_TILE: f32 = 4.;
+
+fn spawn_treemap_tiles(
+    c: &mut ChildSpawnerCommands,
+    items: Vec<TreemapItem>,
+    width: f32,
+    height: f32,
+    depth: usize,
+) {
+    let mut items = items;
+    items.sort_unstable_by_key(|item| std::cmp::Reverse(item.bytes));
+
+    let sizes: Vec<u64> = items.iter().map(|item| item.bytes).collect();
+    let rects = squarify(&sizes, width, height);
+
+    for (item, (x, y, w, h)) in items.into_iter().zip(rects) {
+        if w < TREEMAP_MIN_TILE || h < TREEMAP_MIN_TILE {
+            continue;
+        }
+
+        let mut tile = c.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(x / width * 100.),
+                top: Val::Percent(y / height * 100.),
+                width: Val::Percent(w / width * 100.),
+                height: Val::Percent(h / height * 100.),
+                flex_direction: FlexDirection::Column,
+                border: UiRect::all(Val::Px(1.)),
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BackgroundColor(TREEMAP_COLOURS[depth.min(TREEMAP_COLOURS.len() - 1)]),
+            BorderColor::all(Color::BLACK),
+        ));
+
+        if let Some(project) = item.project {
+            tile.observe(
+                move |mut click: On<Pointer<Click>>,
+                      pl: Res<ProjectList>,
+                      mut sp: ResMut<SelectedProject>| {
+                    click.propagate(false);
+                    if let Some(ple) = pl.iter().find(|ple| ple.kproj.path == project) {
+                        sp.0 = Some(ple.clone());
+                    }
+                },
+            );
+        }
+
+        let label = format!("{} {}", item.label, kondo_lib::pretty_size(item.bytes));
+        let children = item.children;
+
+        tile.with_children(|c| {
+            c.spawn((Text::new(label), font(12.), ThemedText));
+            if !children.is_empty() {
+                c.spawn(Node {
+                    flex_grow: 1.,
+                    ..default()
+                })
+                .with_children(|c| {
+                    // Roughly account for the label taking up the top of the tile
+                    spawn_treemap_tiles(c, children, w, (h - 16.).max(0.), depth + 1);
+                });
+            }
+        });
+    }
+}
+
+fn update_treemap_ui(
+    q: Query<Entity, With<TreemapTag>>,
+    pane: Res<LeftPane>,
+    pl: Res<ProjectList>,
+    view: Res<ProjectListView>,
+    roots: Res<ScanRoots>,
+    mut c: Commands,
+) {
+    if *pane != LeftPane::Treemap
+        || !(pane.is_changed() || pl.is_changed() || view.is_changed() || roots.is_changed())
+    {
+        return;
+    }
+
+    let Ok(treemap) = q.single() else {
+        return;
+    };
+
+    let items = build_treemap_items(&pl, &view, &roots.0);
+
+    c.entity(treemap).despawn_children().with_children(|c| {
+        // Nominal size used for squarifying, tiles are then placed using percentages
+        spawn_treemap_tiles(c, items, 1000., 600., 0);
+    });
+}
@@ -4,15 +4,18 @@ use std::{
     fmt,
     io::{stdin, stdout, Write},
     num::ParseIntError,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::mpsc::{Receiver, Sender, SyncSender},
+    time::{Duration, Instant},
 };
 
 use clap::{Command, CommandFactory, Parser};
 use clap_complete::{generate, Generator, Shell};
 
 use kondo_lib::{
-    dir_size, path_canonicalise, pretty_size, print_elapsed, scan, Project, ScanOptions,
+    dir_size, group_target_dirs, normalise_roots, path_canonicalise, pretty_size, print_elapsed,
+    scan_roots, scan_tagged_caches, ArtifactEscape, CleanOptions, Mount, Processes, Project,
+    ScanOptions, Sweep, SweepOptions, TargetDir,
 };
 
 // Below needs updating every time a new project type is added!
@@ -20,7 +23,7 @@ use kondo_lib::{
 #[command(name = "kondo")]
 /// Kondo recursively cleans project directories.
 ///
-/// Supported project types: Cargo, Node, Unity, SBT, Haskell Stack, Maven, Unreal Engine, Jupyter Notebook, Python, Jupyter Notebooks, CMake, Composer, Pub, Elixir, Swift, Gradle, and .NET projects.
+/// Supported project types: Cargo, Node, Unity, SBT, Haskell Stack, Maven, Unreal Engine, Jupyter Notebook, Python, Jupyter Notebooks, CMake, Meson, Autotools, Bazel, Buck2, Pants, Nix, Ruby, Go, Dune, Rebar3, Julia, renv, LaTeX, Composer, Pub, Elixir, Swift, Gradle, Godot, and .NET projects.
 struct Opt {
     /// The directories to examine. Current directory will be used if DIRS is omitted.
     #[arg(name = "DIRS")]
@@ -34,18 +37,34 @@ struct Opt {
     #[arg(short, long, action = clap::ArgAction::Count, value_parser = clap::value_parser!(u8).range(0..3))]
     quiet: u8,
 
-    /// Clean all found projects without confirmation.
+    /// Clean all found projects without confirmation. Artifact directories whose contents couldn't be verified are skipped, they're only cleaned when confirmed one project at a time.
     #[arg(short, long)]
     all: bool,
 
+    /// Never remove artifact directories whose contents couldn't be verified, even when confirmed.
+    #[arg(long)]
+    verified_only: bool,
+
     /// Follow symbolic links
     #[arg(short = 'L', long)]
     follow_symlinks: bool,
 
-    /// Restrict directory traversal to the root filesystem
+    /// Also look for projects in hidden directories. Version control, .cache, trash, package registry and toolchain directories like .cargo or .m2 are still skipped.
+    #[arg(long)]
+    hidden: bool,
+
+    /// Don't enter other filesystems mounted below each of the DIRS
     #[arg(short, long)]
     same_filesystem: bool,
 
+    /// Enter mounted filesystems of this type even though they're skipped by default, like nfs or fuse.sshfs. Can be given multiple times.
+    #[arg(long, value_name = "TYPE")]
+    allow_fs: Vec<String>,
+
+    /// Don't enter mounted filesystems of this type. Can be given multiple times.
+    #[arg(long, value_name = "TYPE")]
+    deny_fs: Vec<String>,
+
     /// Only directories with a file last modified n units of time ago will be looked at. Ex: 20d. Units are m: minutes, h: hours, d: days, w: weeks, M: months and y: years.
     #[arg(short, long, value_parser = parse_age_filter, default_value = "0d")]
     older: u64,
@@ -65,6 +84,26 @@ struct Opt {
     /// Accept one-letter input without waiting for the Enter key.
     #[arg(long)]
     single_key: bool,
+
+    /// Instead of deleting Cargo's target directory, only remove profiles not built for the given age, incremental caches and artifacts of uninstalled toolchains. Ex: 30d. Uses the same units as --older.
+    #[arg(long, value_parser = parse_age_filter, value_name = "AGE")]
+    cargo_sweep: Option<u64>,
+
+    /// Also clean artifact directories that contain a repository or are another project's root.
+    #[arg(long)]
+    allow_nested: bool,
+
+    /// Also remove what symlinked artifact directories point to outside of the project, and artifact directories with a filesystem mounted inside. By default only the symlink is removed and such directories are left alone. Targets that hold the project or home directory, or don't look like build artifacts, are always kept, and symlinks inside artifact directories are never followed.
+    #[arg(long)]
+    remove_link_targets: bool,
+
+    /// List the directories tagged with a CACHEDIR.TAG that aren't part of a project, instead of cleaning projects.
+    #[arg(long, conflicts_with_all = ["all", "dry_run", "cargo_sweep"])]
+    caches: bool,
+
+    /// Also look at git repositories that aren't a known type of project, reporting their untracked gitignored directories of at least the given size. Ex: 100M. Units are B, K, M, G and T. These are guesses and are only cleaned when confirmed.
+    #[arg(long, value_parser = parse_size_filter, value_name = "SIZE")]
+    gitignored: Option<u64>,
 }
 
 fn prepare_directories(dirs: Vec<PathBuf>) -> Result<Vec<PathBuf>, Box<dyn Error>> {
@@ -94,9 +133,10 @@ fn prepare_directories(dirs: Vec<PathBuf>) -> Result<Vec<PathBuf>, Box<dyn Error
 
             path_canonicalise(&cd, path).ok()
         })
-        .collect();
+        .collect::<Vec<_>>();
 
-    Ok(dirs)
+    // Overlapping directories would have their projects counted and cleaned twice
+    Ok(normalise_roots(&dirs))
 }
 
 #[derive(Debug)]
@@ -124,6 +164,50 @@ impl From<ParseIntError> for ParseAgeFilterError {
 
 impl Error for ParseAgeFilterError {}
 
+#[derive(Debug)]
+pub enum ParseSizeFilterError {
+    ParseIntError(ParseIntError),
+    InvalidUnit,
+}
+
+impl fmt::Display for ParseSizeFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseSizeFilterError::ParseIntError(e) => e.fmt(f),
+            ParseSizeFilterError::InvalidUnit => {
+                "invalid size unit, must be one of B, K, M, G, T".fmt(f)
+            }
+        }
+    }
+}
+
+impl From<ParseIntError> for ParseSizeFilterError {
+    fn from(e: ParseIntError) -> Self {
+        Self::ParseIntError(e)
+    }
+}
+
+impl Error for ParseSizeFilterError {}
+
+pub fn parse_size_filter(size_filter: &str) -> Result<u64, ParseSizeFilterError> {
+    let (digit_end, unit) = size_filter
+        .char_indices()
+        .last()
+        .ok_or(ParseSizeFilterError::InvalidUnit)?;
+
+    let shift = match unit {
+        'B' => 0,
+        'K' => 10,
+        'M' => 20,
+        'G' => 30,
+        'T' => 40,
+        _ => return Err(ParseSizeFilterError::InvalidUnit),
+    };
+
+    let count = size_filter[..digit_end].parse::<u64>()?;
+    Ok(count << shift)
+}
+
 pub fn parse_age_filter(age_filter: &str) -> Result<u64, ParseAgeFilterError> {
     const MINUTE: u64 = 60;
     const HOUR: u64 = MINUTE * 60;
@@ -152,8 +236,117 @@ pub fn parse_age_filter(age_filter: &str) -> Result<u64, ParseAgeFilterError> {
     Ok(seconds)
 }
 
-type DiscoverData = (Project, Vec<(String, u64)>, u64, String);
-type DeleteData = (Project, u64);
+struct ArtifactDir {
+    path: PathBuf,
+    name: String,
+    /// What cleaning frees, only the link for symlinks unless their targets are removed too
+    size: u64,
+    verified: bool,
+    /// Why the directory won't be removed at all
+    refusal: Option<String>,
+    /// How the directory leads outside of the project
+    escape: Option<String>,
+}
+
+impl ArtifactDir {
+    fn new(
+        project: &Project,
+        dir: &Path,
+        size: u64,
+        scan_options: &ScanOptions,
+        clean_options: &CleanOptions,
+    ) -> Self {
+        let mut artifact_dir = Self {
+            path: dir.to_path_buf(),
+            name: project.relative_display(dir),
+            size,
+            verified: project.is_verified_artifact(dir),
+            refusal: project
+                .clean_refusal(dir)
+                .filter(|_| !clean_options.allow_nested)
+                .map(|refusal| refusal.to_string()),
+            escape: None,
+        };
+
+        let remove_targets = clean_options.remove_link_targets;
+        match project.artifact_escape(dir) {
+            Some(escape @ ArtifactEscape::Symlink(_)) if !remove_targets => {
+                artifact_dir.escape = Some(format!("{escape}, only the link will be removed"));
+            }
+            Some(ref escape @ ArtifactEscape::Symlink(ref target)) => {
+                artifact_dir.escape = match project.link_target_refusal(dir, target) {
+                    Some(refusal) => Some(format!(
+                        "{escape}, only the link will be removed as the target {refusal}"
+                    )),
+                    None => {
+                        artifact_dir.size += dir_size(&dir, scan_options);
+                        Some(escape.to_string())
+                    }
+                };
+            }
+            Some(escape @ ArtifactEscape::MountPoint(_)) if !remove_targets => {
+                artifact_dir.refusal = artifact_dir.refusal.or(Some(escape.to_string()));
+            }
+            Some(escape @ ArtifactEscape::NestedSymlink(..)) => {
+                artifact_dir.escape = Some(format!("{escape}, which won't be followed"));
+            }
+            escape => artifact_dir.escape = escape.map(|escape| escape.to_string()),
+        }
+
+        artifact_dir
+    }
+
+    /// Whether the directory is removed when the project is cleaned, unverified directories are
+    /// left unless they're confirmed one project at a time
+    fn cleanable(&self, skip_unverified: bool) -> bool {
+        self.refusal.is_none() && (self.verified || !skip_unverified)
+    }
+}
+
+/// Reclaimable bytes per filesystem, bind mounts are counted with the filesystem they share
+struct FilesystemTotals {
+    mounts: Vec<Mount>,
+    totals: Vec<(Mount, u64)>,
+}
+
+impl FilesystemTotals {
+    fn new() -> Self {
+        Self {
+            mounts: kondo_lib::mounts(),
+            totals: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, path: &Path, bytes: u64) {
+        let Some(mount) = kondo_lib::mount_of(&self.mounts, path) else {
+            return;
+        };
+        match self
+            .totals
+            .iter_mut()
+            .find(|(m, _)| m.device == mount.device)
+        {
+            Some((_, total)) => *total += bytes,
+            None => self.totals.push((mount.clone(), bytes)),
+        }
+    }
+}
+
+enum DiscoverData {
+    Project(Project, Vec<ArtifactDir>, u64, String, Option<Sweep>),
+    /// A Cargo target directory outside of the projects building into it, sent once the scan is
+    /// done so it's known whether it's shared
+    TargetDir(TargetDir, u64, Vec<Project>),
+}
+
+enum DeleteData {
+    /// Only the artifact directories the user was shown are removed
+    Project(Project, Vec<PathBuf>, u64, CleanOptions),
+    /// A Cargo target directory outside of the project, confirmed separately
+    TargetDir(TargetDir, u64, CleanOptions),
+    /// Only the stale content of a Cargo target directory
+    Sweep(Sweep, u64, CleanOptions),
+}
 
 fn discover(
     dirs: Vec<PathBuf>,
@@ -161,27 +354,54 @@ fn discover(
     project_min_age: u64,
     result_sender: SyncSender<DiscoverData>,
     ignored_dirs: &[PathBuf],
+    cargo_sweep: Option<u64>,
+    clean_options: CleanOptions,
 ) {
-    for project in dirs
-        .iter()
-        .flat_map(|dir| scan(dir, scan_options))
+    let mut target_dirs = Vec::new();
+
+    for project in scan_roots(&dirs, scan_options)
         .filter_map(|p| p.ok())
         .filter(|p| ignored_dirs.iter().all(|i| !p.path.starts_with(i)))
     {
-        let artifact_dir_sizes: Vec<_> = project
-            .artifact_dirs()
-            .iter()
-            .copied()
-            .filter_map(
-                |dir| match dir_size(&project.path.join(dir), scan_options) {
-                    0 => None,
-                    size => Some((dir.to_owned(), size)),
-                },
-            )
-            .collect();
-        let project_artifact_bytes = artifact_dir_sizes.iter().map(|(_, bytes)| bytes).sum();
+        let sweep = cargo_sweep.and_then(|max_profile_age| {
+            project.cargo_sweep(&SweepOptions {
+                max_profile_age,
+                ..SweepOptions::default()
+            })
+        });
+
+        let artifact_dir_sizes: Vec<_> = match &sweep {
+            Some(sweep) => sweep
+                .items
+                .iter()
+                .filter(|item| item.size > 0)
+                .map(|item| {
+                    let dir = project.relative_display(&item.path);
+                    ArtifactDir {
+                        path: item.path.clone(),
+                        name: format!("{dir} [{}]", item.reason.description()),
+                        size: item.size,
+                        verified: true,
+                        refusal: None,
+                        escape: None,
+                    }
+                })
+                .collect(),
+            None => project
+                .artifact_sizes(scan_options)
+                .into_iter()
+                .filter(|(_, size)| *size > 0)
+                .map(|(dir, size)| {
+                    ArtifactDir::new(&project, &dir, size, scan_options, &clean_options)
+                })
+                .collect(),
+        };
+        let project_artifact_bytes = artifact_dir_sizes.iter().map(|dir| dir.size).sum();
 
-        if project_artifact_bytes == 0 {
+        // A swept target directory has already been dealt with
+        let target_dir = project.external_target_dir().filter(|_| sweep.is_none());
+
+        if project_artifact_bytes == 0 && target_dir.is_none() {
             continue;
         }
 
@@ -200,46 +420,218 @@ fn discover(
             continue;
         }
 
+        if let Some(target_dir) = target_dir {
+            target_dirs.push((project.clone(), target_dir));
+        }
+        if project_artifact_bytes == 0 {
+            continue;
+        }
+
         if result_sender
-            .send((
+            .send(DiscoverData::Project(
                 project,
                 artifact_dir_sizes,
                 project_artifact_bytes,
                 last_modified_str,
+                sweep,
             ))
             .is_err()
         {
             // interactive prompt has finished, silently finish here
-            break;
+            return;
+        }
+    }
+
+    for (target_dir, projects) in group_target_dirs(target_dirs) {
+        let size = dir_size(&target_dir.path, scan_options);
+        if size > 0
+            && result_sender
+                .send(DiscoverData::TargetDir(target_dir, size, projects))
+                .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// A snapshot of the running processes, shared by the projects checked shortly after one another
+/// as going through every process for each of them is slow
+struct RecentProcesses {
+    processes: Processes,
+    taken: Instant,
+}
+
+impl RecentProcesses {
+    // Processes started since are found by the next snapshot, builds by their locks right away
+    const MAX_AGE: Duration = Duration::from_secs(1);
+
+    fn new() -> Self {
+        Self {
+            processes: Processes::snapshot(),
+            taken: Instant::now(),
+        }
+    }
+
+    fn get(&mut self) -> &Processes {
+        if self.taken.elapsed() > Self::MAX_AGE {
+            *self = Self::new();
         }
+        &self.processes
     }
 }
 
-fn process_deletes(project_recv: Receiver<DeleteData>) -> Vec<(Project, u64)> {
+fn process_deletes(project_recv: Receiver<DeleteData>) -> Vec<DeleteData> {
+    let mut processes = RecentProcesses::new();
     project_recv
         .into_iter()
-        .map(|(project, artifact_bytes)| {
-            project.clean();
-            (project, artifact_bytes)
+        .filter(|delete| match delete {
+            DeleteData::Project(project, artifact_paths, _, options) => {
+                match project.clean_artifacts(artifact_paths, options, processes.get()) {
+                    Ok(()) => true,
+                    // A build may have started since the project was confirmed
+                    Err(in_use) => {
+                        eprintln!("not cleaning {}, it {in_use}", project.name());
+                        false
+                    }
+                }
+            }
+            DeleteData::TargetDir(target_dir, _, options) => {
+                match target_dir.clean_with(options, processes.get()) {
+                    Ok(()) => true,
+                    Err(in_use) => {
+                        eprintln!(
+                            "not cleaning {}, it {in_use}",
+                            target_dir.path.to_string_lossy()
+                        );
+                        false
+                    }
+                }
+            }
+            DeleteData::Sweep(sweep, _, options) => {
+                match sweep.clean_with(options, processes.get()) {
+                    Ok(()) => true,
+                    Err(in_use) => {
+                        eprintln!(
+                            "not sweeping {}, it {in_use}",
+                            sweep.target_dir.to_string_lossy()
+                        );
+                        false
+                    }
+                }
+            }
         })
         .collect()
 }
 
-fn interactive_prompt(
-    projects_recv: Receiver<DiscoverData>,
-    deletes_send: Sender<DeleteData>,
+fn read_choice(single_key: bool) -> String {
+    let mut choice = String::new();
+
+    if single_key {
+        match console::Term::stdout().read_key().unwrap() {
+            console::Key::Char(c) => {
+                choice.push(c);
+                print!("{c}");
+            }
+            console::Key::Enter => {}
+            _ => choice.push_str("unrecognized key"),
+        }
+        println!();
+    } else {
+        stdin().read_line(&mut choice).unwrap();
+    }
+
+    choice
+}
+
+/// How [`interactive_prompt`] asks about each project
+struct PromptOptions {
     quiet: u8,
-    mut clean_all: bool,
+    /// Clean projects without asking, answering all turns it on
+    clean_all: bool,
     default: bool,
     dry_run: bool,
     single_key: bool,
-) -> (usize, u64) {
+}
+
+fn interactive_prompt(
+    projects_recv: Receiver<DiscoverData>,
+    deletes_send: Sender<DeleteData>,
+    prompt_options: PromptOptions,
+    clean_options: CleanOptions,
+) -> (usize, u64, FilesystemTotals) {
+    let PromptOptions {
+        quiet,
+        mut clean_all,
+        default,
+        dry_run,
+        single_key,
+    } = prompt_options;
     let mut total_projects = 0;
     let mut total_bytes = 0;
+    let mut filesystem_totals = FilesystemTotals::new();
+    let mut processes = RecentProcesses::new();
+
+    for discovered in projects_recv {
+        let (project, artifact_dirs, artifact_bytes, last_modified, sweep) = match discovered {
+            DiscoverData::Project(project, artifact_dirs, artifact_bytes, last_modified, sweep) => {
+                (project, artifact_dirs, artifact_bytes, last_modified, sweep)
+            }
+            DiscoverData::TargetDir(target_dir, target_bytes, projects) => {
+                total_bytes += target_bytes;
+                filesystem_totals.add(&target_dir.path, target_bytes);
+
+                let kind = if target_dir.shared {
+                    "shared"
+                } else {
+                    "external"
+                };
+                if quiet == 0 {
+                    println!(
+                        "{} {kind} Cargo target directory ({})",
+                        target_dir.path.to_string_lossy(),
+                        pretty_size(target_bytes),
+                    );
+                    for project in &projects {
+                        println!("  └─ used by {}", project.name());
+                    }
+                }
+                let in_use = target_dir.in_use_with(processes.get());
+                if let Some(in_use) = &in_use {
+                    if quiet == 0 {
+                        println!("  warning: the target directory {in_use}");
+                    }
+                }
+
+                // Other projects may build into it, so never clean it without asking
+                if dry_run {
+                    continue;
+                }
+                if clean_all {
+                    if quiet == 0 {
+                        println!("  skipping, target directories are only cleaned when confirmed");
+                    }
+                    continue;
+                }
+                if !confirm_target_dir(kind, single_key) {
+                    continue;
+                }
+                // Confirmed despite the warning
+                let options = CleanOptions {
+                    allow_in_use: in_use.is_some(),
+                    ..clean_options
+                };
+                let delete = DeleteData::TargetDir(target_dir, target_bytes, options);
+                if let Err(e) = deletes_send.send(delete) {
+                    eprintln!("error sending to delete thread {e}");
+                    break;
+                }
+                continue;
+            }
+        };
 
-    'project_loop: for (project, artifact_dirs, artifact_bytes, last_modified) in projects_recv {
         total_projects += 1;
         total_bytes += artifact_bytes;
+        filesystem_totals.add(&project.path, artifact_bytes);
 
         if quiet == 0 {
             println!(
@@ -247,17 +639,59 @@ fn interactive_prompt(
                 &project.name(),
                 project.type_name(),
             );
-            for (dir, size) in artifact_dirs {
-                println!("  └─ {dir} ({})", pretty_size(size));
+            for dir in &artifact_dirs {
+                let mut notes = vec![pretty_size(dir.size)];
+                if let Some(refusal) = &dir.refusal {
+                    notes.push(format!("won't be removed as it {refusal}"));
+                } else {
+                    notes.extend(dir.escape.clone());
+                    if !dir.verified {
+                        notes.push("unverified".to_string());
+                    }
+                }
+                println!("  └─ {} ({})", dir.name, notes.join(", "));
+            }
+            if let Some(sweep) = &sweep {
+                println!(
+                    "  sweeping reclaims {} of the {} a full clean of {} would",
+                    pretty_size(sweep.reclaimable()),
+                    pretty_size(sweep.target_size),
+                    project.relative_display(&sweep.target_dir),
+                );
+            }
+        }
+
+        let has_cleanable = artifact_dirs.iter().any(|dir| dir.cleanable(false));
+        // A sweep only touches the target directory, which may be outside of the project
+        let in_use = match &sweep {
+            _ if !has_cleanable => None,
+            Some(sweep) => sweep.in_use_with(processes.get()),
+            None => {
+                let artifact_paths: Vec<PathBuf> =
+                    artifact_dirs.iter().map(|dir| dir.path.clone()).collect();
+                project.in_use_with(&artifact_paths, processes.get())
+            }
+        };
+        if let Some(in_use) = &in_use {
+            if quiet == 0 {
+                println!("  warning: the project {in_use}");
             }
         }
 
-        let clean_project = if clean_all {
+        let mut confirmed = false;
+        let clean_project = if !has_cleanable {
+            false
+        } else if in_use.is_some() && clean_all {
+            if quiet == 0 {
+                println!("  skipping, projects in use are only cleaned when confirmed");
+            }
+            false
+        } else if clean_all {
             true
         } else if dry_run {
             false
         } else {
-            loop {
+            confirmed = loop {
                 print!(
                     "  delete above artifact directories? ([{}]es, [n]o, [a]ll, [q]uit): ",
                     {
@@ -269,32 +703,21 @@ fn interactive_prompt(
                     }
                 );
                 stdout().flush().unwrap();
-                let mut choice = String::new();
-
-                if single_key {
-                    match console::Term::stdout().read_key().unwrap() {
-                        console::Key::Char(c) => {
-                            choice.push(c);
-                            print!("{c}");
-                        }
-                        console::Key::Enter => {}
-                        _ => choice.push_str("unrecognized key"),
-                    }
-                    println!();
-                } else {
-                    stdin().read_line(&mut choice).unwrap();
-                }
+                let choice = read_choice(single_key);
 
                 match choice.trim_end() {
                     "y" => break true,
                     "n" => break false,
                     "a" => {
                         clean_all = true;
+                        println!(
+                            "  the remaining projects' unverified directories will be skipped"
+                        );
                         break true;
                     }
                     "q" => {
                         println!();
-                        break 'project_loop;
+                        return (total_projects, total_bytes, filesystem_totals);
                     }
                     "" => {
                         if default {
@@ -306,12 +729,43 @@ fn interactive_prompt(
                     }
                     _ => println!("  invalid choice, please choose between y, n, a, or q."),
                 }
-            }
+            };
+            confirmed
         };
+        // Answering all only skips unverified directories of the projects cleaned after this one
+        let skip_unverified = clean_options.verified_only || (clean_all && !confirmed);
 
-        if clean_project {
+        let clean_dirs: Vec<&ArtifactDir> = artifact_dirs
+            .iter()
+            .filter(|dir| dir.cleanable(skip_unverified))
+            .collect();
+        let clean_bytes: u64 = clean_dirs.iter().map(|dir| dir.size).sum();
+        let skips_unverified = artifact_dirs
+            .iter()
+            .any(|dir| dir.cleanable(false) && !dir.cleanable(skip_unverified));
+        if clean_project && skips_unverified && quiet == 0 {
+            if clean_options.verified_only {
+                println!("  skipping unverified directories");
+            } else {
+                println!("  skipping unverified directories, they're only cleaned when confirmed");
+            }
+        }
+
+        if clean_project && !clean_dirs.is_empty() {
             // TODO: Return an error that indicates a partial failure, not a show stopper
-            if let Err(e) = deletes_send.send((project, artifact_bytes)) {
+            // Confirmed despite the warning
+            let options = CleanOptions {
+                allow_in_use: in_use.is_some(),
+                ..clean_options
+            };
+            let delete = match sweep {
+                Some(sweep) => DeleteData::Sweep(sweep, clean_bytes, options),
+                None => {
+                    let clean_paths = clean_dirs.iter().map(|dir| dir.path.clone()).collect();
+                    DeleteData::Project(project, clean_paths, clean_bytes, options)
+                }
+            };
+            if let Err(e) = deletes_send.send(delete) {
                 eprintln!(
                     "no further projects will be scanned, error sending to delete thread {e}"
                 );
@@ -320,7 +774,41 @@ fn interactive_prompt(
         }
     }
 
-    (total_projects, total_bytes)
+    (total_projects, total_bytes, filesystem_totals)
+}
+
+fn confirm_target_dir(kind: &str, single_key: bool) -> bool {
+    loop {
+        print!("  delete the {kind} target directory? ([y]es, [N]o): ");
+        stdout().flush().unwrap();
+
+        match read_choice(single_key).trim_end() {
+            "y" => break true,
+            "n" | "" => break false,
+            _ => println!("  invalid choice, please choose between y or n."),
+        }
+    }
+}
+
+fn list_tagged_caches(dirs: &[PathBuf], scan_options: &ScanOptions, ignored_dirs: &[PathBuf]) {
+    let mut total_caches = 0;
+    let mut total_bytes = 0;
+
+    for dir in dirs {
+        for cache in scan_tagged_caches(dir, scan_options)
+            .filter(|p| ignored_dirs.iter().all(|i| !p.starts_with(i)))
+        {
+            let size = dir_size(&cache, scan_options);
+            println!("{} ({})", cache.display(), pretty_size(size));
+            total_caches += 1;
+            total_bytes += size;
+        }
+    }
+
+    println!(
+        "Tagged caches: {total_caches}, Bytes: {}",
+        pretty_size(total_bytes)
+    );
 }
 
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
@@ -347,12 +835,23 @@ fn main() -> Result<(), Box<dyn Error>> {
     let scan_options: ScanOptions = ScanOptions {
         follow_symlinks: opt.follow_symlinks,
         same_file_system: opt.same_filesystem,
+        gitignored_min_size: opt.gitignored,
+        allowed_filesystems: std::mem::take(&mut opt.allow_fs),
+        denied_filesystems: std::mem::take(&mut opt.deny_fs),
+        include_hidden: opt.hidden,
     };
 
     let (proj_discover_send, proj_discover_recv) = std::sync::mpsc::sync_channel::<DiscoverData>(5);
-    let (proj_delete_send, proj_delete_recv) = std::sync::mpsc::channel::<(Project, u64)>();
+    let (proj_delete_send, proj_delete_recv) = std::sync::mpsc::channel::<DeleteData>();
 
     let project_min_age = opt.older;
+    let cargo_sweep = opt.cargo_sweep;
+    let clean_options = CleanOptions {
+        verified_only: opt.verified_only,
+        allow_nested: opt.allow_nested,
+        remove_link_targets: opt.remove_link_targets,
+        allow_in_use: false,
+    };
     let ignored_dirs = {
         let cd = current_dir()?;
 
@@ -362,6 +861,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             .collect::<Result<Vec<_>, _>>()?
     };
 
+    if opt.caches {
+        list_tagged_caches(&dirs, &scan_options, &ignored_dirs);
+        return Ok(());
+    }
+
     std::thread::spawn(move || {
         discover(
             dirs,
@@ -369,19 +873,24 @@ fn main() -> Result<(), Box<dyn Error>> {
             project_min_age,
             proj_discover_send,
             &ignored_dirs,
+            cargo_sweep,
+            clean_options,
         );
     });
 
     let delete_handle = std::thread::spawn(move || process_deletes(proj_delete_recv));
 
-    let (total_projects, total_bytes) = interactive_prompt(
+    let (total_projects, total_bytes, filesystem_totals) = interactive_prompt(
         proj_discover_recv,
         proj_delete_send,
-        opt.quiet,
-        opt.all,
-        opt.default,
-        opt.dry_run,
-        opt.single_key,
+        PromptOptions {
+            quiet: opt.quiet,
+            clean_all: opt.all,
+            default: opt.default,
+            dry_run: opt.dry_run,
+            single_key: opt.single_key,
+        },
+        clean_options,
     );
 
     let delete_results = match delete_handle.join() {
@@ -393,8 +902,18 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
 
     if opt.quiet < 2 {
-        let projects_cleaned = delete_results.len();
-        let bytes_deleted = delete_results.iter().map(|(_, bytes)| bytes).sum();
+        let projects_cleaned = delete_results
+            .iter()
+            .filter(|d| matches!(d, DeleteData::Project(..) | DeleteData::Sweep(..)))
+            .count();
+        let bytes_deleted = delete_results
+            .iter()
+            .map(|d| match d {
+                DeleteData::Project(_, _, bytes, _)
+                | DeleteData::TargetDir(_, bytes, _)
+                | DeleteData::Sweep(_, bytes, _) => bytes,
+            })
+            .sum();
 
         println!(
             "Projects cleaned: {}/{}, Bytes deleted: {} / {}",
@@ -403,6 +922,19 @@ fn main() -> Result<(), Box<dyn Error>> {
             pretty_size(bytes_deleted),
             pretty_size(total_bytes)
         );
+
+        // Only worth breaking down when the projects are spread across filesystems
+        if filesystem_totals.totals.len() > 1 {
+            for (mount, bytes) in &filesystem_totals.totals {
+                println!(
+                    "  {} ({}, {}): {}",
+                    mount.mount_point.display(),
+                    mount.fs_type,
+                    mount.source,
+                    pretty_size(*bytes)
+                );
+            }
+        }
     }
 
     Ok(())